@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::hit::{Hit, HitRecord};
+use crate::ray::Ray;
+use crate::texture::Texture;
+
+/// Cuts holes into another object wherever its opacity, the luminance of a texture, is low
+pub struct AlphaMask {
+    object: Box<dyn Hit>,
+    opacity: Arc<dyn Texture>,
+    /// Hits are discarded below this opacity, or randomly in proportion to it if there's none
+    threshold: Option<f64>,
+}
+
+impl AlphaMask {
+    /// Discards hits where the opacity is below the threshold, giving crisp cutouts
+    pub fn new(object: Box<dyn Hit>, opacity: Arc<dyn Texture>, threshold: f64) -> AlphaMask {
+        AlphaMask {
+            object,
+            opacity,
+            threshold: Some(threshold),
+        }
+    }
+
+    /// Randomly lets rays pass with a probability of one minus the opacity, which makes the
+    /// object partially transparent on average
+    pub fn stochastic(object: Box<dyn Hit>, opacity: Arc<dyn Texture>) -> AlphaMask {
+        AlphaMask {
+            object,
+            opacity,
+            threshold: None,
+        }
+    }
+}
+
+impl Hit for AlphaMask {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rng = rand::thread_rng();
+        let mut t_min = t_min;
+
+        loop {
+            let rec = self.object.hit(r, t_min, t_max)?;
+            let opacity = self.opacity.value(rec.u, rec.v, rec.point).luminance();
+
+            let opaque = match self.threshold {
+                Some(threshold) => opacity >= threshold,
+                None => rng.gen::<f64>() < opacity,
+            };
+            if opaque {
+                return Some(rec);
+            }
+
+            // Look for the next intersection behind the transparent one
+            t_min = rec.t + 0.001;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+    use crate::texture::{Checker, SolidColor};
+    use crate::vec3::Vec3;
+
+    fn sphere() -> Box<dyn Hit> {
+        let white = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
+        Box::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, white))
+    }
+
+    fn gray(value: f64) -> Arc<dyn Texture> {
+        Arc::new(SolidColor::new(Color::new(value, value, value)))
+    }
+
+    #[test]
+    fn cutout_hits_behind_holes() {
+        let r = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+
+        assert!(AlphaMask::new(sphere(), gray(0.2), 0.5)
+            .hit(&r, 0.001, f64::INFINITY)
+            .is_none());
+        let rec = AlphaMask::new(sphere(), gray(0.8), 0.5)
+            .hit(&r, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.t - 2.0).abs() < 1.0e-9);
+
+        // The front of the sphere falls into a transparent square, the back into an opaque one
+        let checker = Arc::new(Checker::new(0.5, gray(1.0), gray(0.0)));
+        let rec = AlphaMask::new(sphere(), checker, 0.5)
+            .hit(&r, 0.001, f64::INFINITY)
+            .unwrap();
+        assert!((rec.t - 4.0).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn stochastic_opacity() {
+        let mask = AlphaMask::stochastic(sphere(), gray(0.3));
+        let r = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let samples = 20000;
+        let hits = (0..samples)
+            .filter(|_| mask.hit(&r, 0.001, f64::INFINITY).is_some())
+            .count();
+
+        // The ray passes through both sides of the sphere with a probability of 0.7 each
+        let fraction = hits as f64 / samples as f64;
+        assert!((fraction - 0.51).abs() < 0.02, "{}", fraction);
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    f64::consts::PI,
+    io::{self, ErrorKind},
+};
+
+use rand::Rng;
+
+use crate::point3::Point3;
+use crate::texture::{ImageTexture, Texture};
+use crate::vec3::Vec3;
+
+/// The shape of the opening of a lens, which is the shape out of focus highlights (bokeh) take
+pub enum Aperture {
+    Circle,
+    /// A regular polygon formed by `blades` straight diaphragm blades, rotated by `rotation`
+    /// degrees
+    Polygon {
+        blades: u32,
+        rotation: f64,
+    },
+    /// The bright parts of an image, like a shape cut out of a cover in front of the lens
+    Mask(ApertureMask),
+}
+
+/// An image turned into a distribution over the lens, which is opened proportionally to the
+/// luminance of the pixels
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    /// Cumulative distribution of picking each pixel, in rows from top to bottom
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    /// Fails if the image is black everywhere, which would close the aperture completely
+    pub fn new(image: &ImageTexture) -> io::Result<ApertureMask> {
+        let (width, height) = image.size();
+        let origin = Point3::new(0.0, 0.0, 0.0);
+
+        let mut total = 0.0;
+        let mut cdf = Vec::with_capacity(width * height);
+        for j in 0..height {
+            for i in 0..width {
+                let u = (i as f64 + 0.5) / (width as f64);
+                let v = 1.0 - (j as f64 + 0.5) / (height as f64);
+                total += image.value(u, v, origin).luminance().max(0.0);
+                cdf.push(total);
+            }
+        }
+        if total <= 0.0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Aperture mask is completely closed",
+            ));
+        }
+
+        Ok(ApertureMask { width, height, cdf })
+    }
+
+    fn sample(&self) -> Vec3 {
+        let mut rng = rand::thread_rng();
+
+        let xi = rng.gen::<f64>() * self.cdf.last().unwrap();
+        let index = self
+            .cdf
+            .partition_point(|&c| c <= xi)
+            .min(self.cdf.len() - 1);
+        let (i, j) = (index % self.width, index / self.width);
+
+        // Uniformly within the pixel, with the image covering the square around the unit disk
+        let x = (i as f64 + rng.gen::<f64>()) / (self.width as f64);
+        let y = (j as f64 + rng.gen::<f64>()) / (self.height as f64);
+        Vec3::new(2.0 * x - 1.0, 1.0 - 2.0 * y, 0.0)
+    }
+}
+
+impl Aperture {
+    /// A point on the lens, within the unit disk for the circle and the polygons and within the
+    /// square around it for masks
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Aperture::Circle => Vec3::random_in_unit_disk(),
+            Aperture::Polygon { blades, rotation } => {
+                let mut rng = rand::thread_rng();
+                let blades = (*blades).max(3);
+
+                // Uniformly within one of the triangles between the center and the edges
+                let edge = rng.gen_range(0..blades) as f64;
+                let angle = 2.0 * PI / (blades as f64);
+                let phi0 = rotation.to_radians() + edge * angle;
+                let phi1 = phi0 + angle;
+
+                let su = rng.gen::<f64>().sqrt();
+                let b = rng.gen::<f64>();
+                let (b0, b1) = (su * (1.0 - b), su * b);
+
+                Vec3::new(
+                    b0 * phi0.cos() + b1 * phi1.cos(),
+                    b0 * phi0.sin() + b1 * phi1.sin(),
+                    0.0,
+                )
+            }
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::color::Color;
+
+    #[test]
+    fn closed_mask() {
+        let black = ImageTexture::new(2, 2, vec![Color::new(0.0, 0.0, 0.0); 4]);
+        assert!(ApertureMask::new(&black).is_err());
+    }
+
+    #[test]
+    fn samples_open_pixels() {
+        // Only the lower right quarter is open
+        let mut pixels = vec![Color::new(0.0, 0.0, 0.0); 4];
+        pixels[3] = Color::new(1.0, 1.0, 1.0);
+        let mask = ApertureMask::new(&ImageTexture::new(2, 2, pixels)).unwrap();
+
+        for _ in 0..1000 {
+            let p = mask.sample();
+            assert!((0.0..=1.0).contains(&p.x()) && (-1.0..=0.0).contains(&p.y()));
+        }
+    }
+}
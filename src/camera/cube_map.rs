@@ -0,0 +1,79 @@
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+use super::Camera;
+
+/// The six faces of a cube around a point, aligned with the world axes and laid out in a grid of
+/// three by two: +x, -x and +y in the top row and -y, +z and -z in the bottom row. Each face is a
+/// 90° view, so the image should have an aspect ratio of 3:2 for square faces.
+pub struct CubeMap {
+    origin: Point3,
+    /// The viewing direction and the up direction of each face, in layout order
+    faces: [(Vec3, Vec3); 6],
+}
+
+impl CubeMap {
+    pub fn new(look_from: Point3) -> Self {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+
+        Self {
+            origin: look_from,
+            faces: [
+                (x, y),
+                ((-1.0) * x, y),
+                (y, (-1.0) * z),
+                ((-1.0) * y, z),
+                (z, y),
+                ((-1.0) * z, y),
+            ],
+        }
+    }
+}
+
+impl Camera for CubeMap {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let column = ((3.0 * s) as usize).min(2);
+        let row = if t >= 0.5 { 0 } else { 1 };
+        let (forward, up) = self.faces[3 * row + column];
+        let right = forward.cross(up);
+
+        // Position within the face, from -1 to 1
+        let a = 2.0 * (3.0 * s - column as f64) - 1.0;
+        let b = 2.0 * (2.0 * t - (1 - row) as f64) - 1.0;
+
+        Some(Ray::new(self.origin, forward + a * right + b * up))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_in_a_three_by_two_grid() {
+        let camera = CubeMap::new(Point3::new(1.0, 2.0, 3.0));
+
+        let centers = [
+            (1.0 / 6.0, 0.75, Vec3::new(1.0, 0.0, 0.0)),
+            (0.5, 0.75, Vec3::new(-1.0, 0.0, 0.0)),
+            (5.0 / 6.0, 0.75, Vec3::new(0.0, 1.0, 0.0)),
+            (1.0 / 6.0, 0.25, Vec3::new(0.0, -1.0, 0.0)),
+            (0.5, 0.25, Vec3::new(0.0, 0.0, 1.0)),
+            (5.0 / 6.0, 0.25, Vec3::new(0.0, 0.0, -1.0)),
+        ];
+        for (s, t, expected) in centers {
+            let r = camera.get_ray(s, t).unwrap();
+            assert!((r.origin() - Point3::new(1.0, 2.0, 3.0)).length() < 1.0e-12);
+            assert!((r.direction() - expected).length() < 1.0e-12);
+        }
+
+        // The upper left corner of the +x face, and the lower right one of the -z face
+        let corner = camera.get_ray(0.0, 1.0).unwrap().direction();
+        assert!((corner - Vec3::new(1.0, 1.0, -1.0)).length() < 1.0e-12);
+        let corner = camera.get_ray(1.0, 0.0).unwrap().direction();
+        assert!((corner - Vec3::new(1.0, -1.0, -1.0)).length() < 1.0e-12);
+    }
+}
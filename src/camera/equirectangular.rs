@@ -0,0 +1,113 @@
+use std::f64::consts::PI;
+
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+use super::Camera;
+
+/// A 360° panorama in latitude-longitude layout, with `look_at` in the center of the image and
+/// the poles at its top and bottom edge, best rendered at an aspect ratio of 2:1
+pub struct Equirectangular {
+    origin: Point3,
+    cu: Vec3,
+    cv: Vec3,
+    cw: Vec3,
+    /// Sideways offset of the eye for omni-directional stereo
+    eye_offset: f64,
+}
+
+impl Equirectangular {
+    pub fn new(look_from: Point3, look_at: Point3, v_up: Vec3) -> Self {
+        let cw = (look_from - look_at).normalized();
+        let cu = v_up.cross(cw).normalized();
+        let cv = cw.cross(cu);
+
+        Self {
+            origin: look_from,
+            cu,
+            cv,
+            cw,
+            eye_offset: 0.0,
+        }
+    }
+
+    /// Makes the panorama one eye of an omni-directional stereo pair, where rays start `offset`
+    /// to the right of the center, perpendicular to their direction, so that every column sees
+    /// the world as if looking straight at it
+    pub fn with_eye_offset(self, offset: f64) -> Equirectangular {
+        Equirectangular {
+            eye_offset: offset,
+            ..self
+        }
+    }
+}
+
+impl Camera for Equirectangular {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let longitude = 2.0 * PI * (s - 0.5);
+        let latitude = PI * (t - 0.5);
+
+        let direction = (latitude.cos() * longitude.sin()) * self.cu + latitude.sin() * self.cv
+            - (latitude.cos() * longitude.cos()) * self.cw;
+
+        // To the right of the direction, on the horizontal circle the eyes turn on
+        let right = longitude.cos() * self.cu + longitude.sin() * self.cw;
+
+        Some(Ray::new(self.origin + self.eye_offset * right, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longitude_across_latitude_up() {
+        let camera = Equirectangular::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(1.0, 2.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+
+        for (s, t, expected) in [
+            (0.5, 0.5, Vec3::new(0.0, 0.0, -1.0)),
+            (0.75, 0.5, Vec3::new(1.0, 0.0, 0.0)),
+            (0.25, 0.5, Vec3::new(-1.0, 0.0, 0.0)),
+            (0.0, 0.5, Vec3::new(0.0, 0.0, 1.0)),
+            (0.5, 1.0, Vec3::new(0.0, 1.0, 0.0)),
+            (0.3, 0.0, Vec3::new(0.0, -1.0, 0.0)),
+            (0.625, 0.75, Vec3::new(0.5, 2.0_f64.sqrt() / 2.0, -0.5)),
+        ] {
+            let r = camera.get_ray(s, t).unwrap();
+            assert!((r.origin() - Point3::new(1.0, 2.0, 3.0)).length() < 1.0e-12);
+            assert!((r.direction() - expected).length() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn omni_directional_eyes_sit_beside_each_ray() {
+        let center = Point3::new(1.0, 2.0, 3.0);
+        let panorama =
+            || Equirectangular::new(center, Point3::new(4.0, 2.0, 7.0), Vec3::new(0.0, 1.0, 0.0));
+        let (left, right) = (
+            panorama().with_eye_offset(-0.3),
+            panorama().with_eye_offset(0.3),
+        );
+
+        for (s, t) in [(0.5, 0.5), (0.1, 0.2), (0.8, 0.6), (0.35, 0.95)] {
+            let (l, r) = (left.get_ray(s, t).unwrap(), right.get_ray(s, t).unwrap());
+            assert!((l.direction() - r.direction()).length() < 1.0e-12);
+
+            let offset = r.origin() - center;
+            assert!((offset.length() - 0.3).abs() < 1.0e-12);
+            assert!(offset.dot(r.direction()).abs() < 1.0e-12);
+            assert!(offset.y().abs() < 1.0e-12);
+            assert!(((l.origin() - center) + offset).length() < 1.0e-12);
+
+            // To the right when looking along the ray with the up vector up
+            let right_side = r.direction().cross(Vec3::new(0.0, 1.0, 0.0));
+            assert!(offset.dot(right_side) > 0.0);
+        }
+    }
+}
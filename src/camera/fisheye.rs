@@ -0,0 +1,126 @@
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+use super::Camera;
+
+/// How a fisheye lens maps the angle from its axis to the distance from the image center
+#[derive(Clone, Copy)]
+pub enum FisheyeProjection {
+    /// The distance is proportional to the angle
+    Equidistant,
+    /// Equal solid angles cover equal areas of the image
+    Equisolid,
+}
+
+/// A fisheye lens whose image circle spans the height of the image, continuing the projection
+/// into the corners of wider images
+pub struct Fisheye {
+    origin: Point3,
+    cu: Vec3,
+    cv: Vec3,
+    cw: Vec3,
+    aspect_ratio: f64,
+    /// Half of the field of view in radians
+    theta_max: f64,
+    projection: FisheyeProjection,
+}
+
+impl Fisheye {
+    /// `fov` is the field of view across the image circle in degrees, up to 360°
+    pub fn new(
+        look_from: Point3,
+        look_at: Point3,
+        v_up: Vec3,
+        fov: f64,
+        aspect_ratio: f64,
+        projection: FisheyeProjection,
+    ) -> Self {
+        let cw = (look_from - look_at).normalized();
+        let cu = v_up.cross(cw).normalized();
+        let cv = cw.cross(cu);
+
+        Self {
+            origin: look_from,
+            cu,
+            cv,
+            cw,
+            aspect_ratio,
+            theta_max: 0.5 * fov.clamp(0.0, 360.0).to_radians(),
+            projection,
+        }
+    }
+}
+
+impl Camera for Fisheye {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        // Position relative to the image circle, whose radius is one
+        let x = 2.0 * self.aspect_ratio * (s - 0.5);
+        let y = 2.0 * (t - 0.5);
+        let r = (x.powi(2) + y.powi(2)).sqrt();
+
+        let theta = match self.projection {
+            FisheyeProjection::Equidistant => r * self.theta_max,
+            FisheyeProjection::Equisolid => {
+                2.0 * (r * (0.5 * self.theta_max).sin()).clamp(-1.0, 1.0).asin()
+            }
+        };
+        let (cos_phi, sin_phi) = if r > 0.0 { (x / r, y / r) } else { (1.0, 0.0) };
+
+        let direction = (theta.sin() * cos_phi) * self.cu + (theta.sin() * sin_phi) * self.cv
+            - theta.cos() * self.cw;
+
+        Some(Ray::new(self.origin, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fisheye(aspect_ratio: f64, projection: FisheyeProjection) -> Fisheye {
+        Fisheye::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            180.0,
+            aspect_ratio,
+            projection,
+        )
+    }
+
+    #[test]
+    fn equidistant_angles_grow_with_the_radius() {
+        let camera = fisheye(2.0, FisheyeProjection::Equidistant);
+        let diagonal = 0.5_f64.sqrt();
+
+        for (s, t, expected) in [
+            (0.5, 0.5, Vec3::new(0.0, 0.0, -1.0)),
+            (0.75, 0.5, Vec3::new(1.0, 0.0, 0.0)),
+            (0.5, 0.0, Vec3::new(0.0, -1.0, 0.0)),
+            (0.625, 0.5, Vec3::new(diagonal, 0.0, -diagonal)),
+            (0.5, 0.75, Vec3::new(0.0, diagonal, -diagonal)),
+            // Beyond the image circle, looking backwards
+            (1.0, 0.5, Vec3::new(0.0, 0.0, 1.0)),
+        ] {
+            let direction = camera.get_ray(s, t).unwrap().direction();
+            assert!((direction - expected).length() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn equisolid_areas_cover_equal_solid_angles() {
+        let camera = fisheye(1.0, FisheyeProjection::Equisolid);
+        assert!(
+            (camera.get_ray(1.0, 0.5).unwrap().direction() - Vec3::new(1.0, 0.0, 0.0)).length()
+                < 1.0e-12
+        );
+
+        // The solid angle of the cone within a radius, 2π(1 - cos θ), grows with the area of the
+        // disk, where the half of the sphere in front of the lens fills all of the image circle
+        for r in [0.1, 0.25, 0.5, 0.9] {
+            let cos_theta = -camera.get_ray(0.5 + 0.5 * r, 0.5).unwrap().direction().z();
+            assert!(((1.0 - cos_theta) - r * r).abs() < 1.0e-12);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::material::Dispersion;
+use crate::vec3::Vec3;
+
+/// Wavelengths (in micrometers) of the Fraunhofer F, d and C lines, which define the index of
+/// refraction and the Abbe number of glasses
+const LINE_F: f64 = 0.4861;
+const LINE_D: f64 = 0.5876;
+const LINE_C: f64 = 0.6563;
+
+/// The wavelength (in nanometers) of the d line, used for lenses without dispersion
+pub const REFERENCE_WAVELENGTH: f64 = 1000.0 * LINE_D;
+
+/// A 50mm f/2 double Gauss lens (US patent 2,673,491, scaled from 100mm), with Abbe numbers for
+/// typical crown and flint glasses of its indices
+const DOUBLE_GAUSS: &str = "# radius thickness ior aperture abbe
+29.475    3.76   1.67   25.2  47.2
+84.83     0.12   1      25.2
+19.275    4.025  1.67   23    47.2
+40.77     3.275  1.699  23    30.1
+12.75     5.705  1      18
+0         4.5    0      17.1
+-14.495   1.18   1.603  17    38.0
+40.77     6.065  1.658  20    50.9
+-20.385   0.19   1      20
+437.065   3.22   1.717  20    47.9
+-39.73    0      1      20
+";
+
+/// A single spherical surface of a lens, or the aperture stop if it's flat
+struct LensElement {
+    /// Radius of curvature, positive when the center is towards the film, zero for the stop
+    radius: f64,
+    /// Distance to the next element towards the film
+    thickness: f64,
+    /// The glass between this element and the next one, or `None` for air
+    glass: Option<Dispersion>,
+    aperture_radius: f64,
+}
+
+/// The elements of a compound lens, from the front towards the film, with lengths in
+/// millimeters.
+///
+/// The lens is on the positive z-axis in front of the film at the origin. The thickness of the
+/// last element, the distance to the film, follows from focusing instead.
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+}
+
+impl LensSystem {
+    pub fn double_gauss() -> LensSystem {
+        LensSystem::parse(DOUBLE_GAUSS).expect("Invalid built-in lens")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<LensSystem> {
+        LensSystem::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a lens prescription with one element per line, starting at the front: the radius of
+    /// curvature (zero for the aperture stop), the thickness, the index of refraction at the d
+    /// line (zero or one for air) and the aperture diameter, optionally followed by the Abbe
+    /// number of the glass for its dispersion. Lines starting with `#` are comments.
+    pub fn parse(text: &str) -> io::Result<LensSystem> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let mut elements = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let values = line
+                .split_whitespace()
+                .map(|token| {
+                    token
+                        .parse::<f64>()
+                        .map_err(|_| invalid(&format!("Invalid number {:?}", token)))
+                })
+                .collect::<io::Result<Vec<f64>>>()?;
+            let (radius, thickness, ior, aperture, abbe) = match values[..] {
+                [radius, thickness, ior, aperture] => (radius, thickness, ior, aperture, None),
+                [radius, thickness, ior, aperture, abbe] => {
+                    (radius, thickness, ior, aperture, Some(abbe))
+                }
+                _ => return Err(invalid("Expected four or five values per lens element")),
+            };
+
+            let glass = if ior <= 1.0 {
+                None
+            } else {
+                // Cauchy's equation through the index at the d line, with the spread between the
+                // F and C lines given by the Abbe number
+                let b = match abbe {
+                    Some(abbe) if abbe > 0.0 => {
+                        (ior - 1.0) / (abbe * (1.0 / LINE_F.powi(2) - 1.0 / LINE_C.powi(2)))
+                    }
+                    _ => 0.0,
+                };
+                Some(Dispersion::Cauchy {
+                    a: ior - b / LINE_D.powi(2),
+                    b,
+                })
+            };
+
+            elements.push(LensElement {
+                radius,
+                thickness,
+                glass,
+                aperture_radius: aperture / 2.0,
+            });
+        }
+
+        if elements.is_empty() {
+            return Err(invalid("Lens without elements"));
+        }
+
+        Ok(LensSystem { elements })
+    }
+
+    /// Whether the index of refraction of any of the glasses depends on the wavelength
+    pub fn is_dispersive(&self) -> bool {
+        self.elements
+            .iter()
+            .any(|element| matches!(element.glass, Some(Dispersion::Cauchy { b, .. }) if b != 0.0))
+    }
+
+    pub fn rear_aperture_radius(&self) -> f64 {
+        self.elements.last().unwrap().aperture_radius
+    }
+
+    /// Traces a ray from the film through the lens, with the rear element at `film_distance` in
+    /// front of the film. Returns the ray leaving the front element, or `None` if it's blocked
+    /// by an aperture or totally reflected.
+    pub fn trace(
+        &self,
+        film_distance: f64,
+        mut origin: Vec3,
+        mut direction: Vec3,
+        wavelength: f64,
+    ) -> Option<(Vec3, Vec3)> {
+        let mut z = film_distance;
+
+        for (index, element) in self.elements.iter().enumerate().rev() {
+            if index + 1 < self.elements.len() {
+                z += element.thickness;
+            }
+
+            // Find where the ray crosses the element
+            let (t, normal) = if element.radius == 0.0 {
+                if direction.z() <= 0.0 {
+                    return None;
+                }
+                ((z - origin.z()) / direction.z(), Vec3::new(0.0, 0.0, -1.0))
+            } else {
+                let center = Vec3::new(0.0, 0.0, z - element.radius);
+                let t = Self::intersect_surface(origin, direction, center, element.radius)?;
+                let normal = (origin + t * direction - center).normalized();
+                (t, normal)
+            };
+
+            origin += t * direction;
+            if origin.x().powi(2) + origin.y().powi(2) > element.aperture_radius.powi(2) {
+                return None;
+            }
+
+            if element.radius != 0.0 {
+                let ior = |glass: &Option<Dispersion>| {
+                    glass.map_or(1.0, |glass| glass.index_of_refraction(wavelength))
+                };
+                let eta_i = ior(&element.glass);
+                let eta_t = match index {
+                    0 => 1.0,
+                    _ => ior(&self.elements[index - 1].glass),
+                };
+
+                direction = Self::refract(direction.normalized(), normal, eta_i / eta_t)?;
+            }
+        }
+
+        Some((origin, direction))
+    }
+
+    /// The ray parameter of the first hit of a spherical surface, on the cap around its vertex
+    fn intersect_surface(origin: Vec3, direction: Vec3, center: Vec3, radius: f64) -> Option<f64> {
+        let oc = origin - center;
+        let a = direction.length().powi(2);
+        let half_b = oc.dot(direction);
+        let c = oc.length().powi(2) - radius.powi(2);
+
+        let discriminant = half_b.powi(2) - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        // The vertex is at `center + radius` along the z-axis, so the cap is on that side
+        let sqrtd = discriminant.sqrt();
+        [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a]
+            .into_iter()
+            .find(|&t| t > 1.0e-9 && (origin.z() + t * direction.z() - center.z()) * radius > 0.0)
+    }
+
+    /// Refracts the unit vector `direction` at a surface with the given normal, or `None` on
+    /// total internal reflection
+    fn refract(direction: Vec3, normal: Vec3, eta: f64) -> Option<Vec3> {
+        let normal = if direction.dot(normal) > 0.0 {
+            (-1.0) * normal
+        } else {
+            normal
+        };
+
+        let cos_i = -direction.dot(normal);
+        let sin2_t = eta.powi(2) * (1.0 - cos_i.powi(2)).max(0.0);
+        if sin2_t >= 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+
+        Some(eta * direction + (eta * cos_i - cos_t) * normal)
+    }
+
+    /// The distance between the film and the rear element for objects at `distance` in front of
+    /// the film to be in focus, found by bisection on where a paraxial ray from the center of the
+    /// film converges. Returns `None` if the lens can't focus that close.
+    pub fn focus(&self, distance: f64) -> Option<f64> {
+        let length: f64 = self.elements[..self.elements.len() - 1]
+            .iter()
+            .map(|element| element.thickness)
+            .sum();
+        if distance <= length {
+            return None;
+        }
+
+        // How far in front of the film a ray from its center converges, as its reciprocal so
+        // that diverging rays, which converge beyond infinity, continue the scale
+        let height = 1.0e-3 * self.rear_aperture_radius();
+        let vergence = |film_distance: f64| -> Option<f64> {
+            let direction = Vec3::new(height, 0.0, film_distance);
+            let (origin, direction) = self.trace(
+                film_distance,
+                Vec3::new(0.0, 0.0, 0.0),
+                direction,
+                REFERENCE_WAVELENGTH,
+            )?;
+            let t = -origin.x() / direction.x();
+
+            Some(1.0 / (origin.z() + t * direction.z()))
+        };
+        let error =
+            |film_distance: f64| vergence(film_distance).map(|vergence| vergence - 1.0 / distance);
+
+        // Moving the film away from the lens beyond its focal length focuses closer, down to the
+        // closest distance with the film at twice the focal length. Search upwards for the first
+        // bracket, in small steps to not skip both solutions for objects close to that distance.
+        let mut focused_farther = None;
+        let mut high = 1.0e-3;
+        let mut low = loop {
+            match error(high) {
+                Some(error) if error < 0.0 => focused_farther = Some(high),
+                Some(_) if focused_farther.is_some() => break focused_farther?,
+                _ => {}
+            }
+            if high > 1.0e4 {
+                return None;
+            }
+            high *= 1.1;
+        };
+
+        for _ in 0..100 {
+            let middle = 0.5 * (low + high);
+            match error(middle) {
+                Some(error) if error < 0.0 => low = middle,
+                _ => high = middle,
+            }
+        }
+
+        Some(0.5 * (low + high))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A biconvex lens with a focal length of about 50mm, thin enough for the thin lens equation
+    const BICONVEX: &str = "50 0.1 1.5 4\n-50 0 1 4\n";
+
+    #[test]
+    fn column_counts() {
+        assert!(LensSystem::parse("50 2 1.5").is_err());
+        assert!(LensSystem::parse("50 2 1.5 20 60 1").is_err());
+        assert!(LensSystem::parse("50 2 1.5 20\n-50 0 1").is_err());
+        assert!(LensSystem::parse("50 2 glass 20").is_err());
+    }
+
+    #[test]
+    fn empty_prescription() {
+        assert!(LensSystem::parse("").is_err());
+        assert!(LensSystem::parse("# radius thickness ior aperture\n\n").is_err());
+    }
+
+    #[test]
+    fn built_in_lens() {
+        let lens = LensSystem::double_gauss();
+        assert_eq!(lens.elements.len(), 11);
+        assert_eq!(lens.rear_aperture_radius(), 10.0);
+        assert!(lens.is_dispersive());
+        assert!(!LensSystem::parse(BICONVEX).unwrap().is_dispersive());
+
+        // A 50mm lens focused at infinity sits about its focal length in front of the film
+        let film_distance = lens.focus(1.0e9).unwrap();
+        assert!((film_distance - 40.0).abs() < 15.0, "{}", film_distance);
+    }
+
+    #[test]
+    fn thin_lens_focus() {
+        let lens = LensSystem::parse(BICONVEX).unwrap();
+        let focal_length = 1.0 / (0.5 * (1.0 / 50.0 + 1.0 / 50.0));
+
+        for distance in [300.0, 1000.0, 10000.0] {
+            let film_distance = lens.focus(distance).unwrap();
+            let object_distance = distance - film_distance - 0.1;
+            let expected = 1.0 / (1.0 / focal_length - 1.0 / object_distance);
+            assert!(
+                (film_distance - expected).abs() < 0.1,
+                "{} isn't {}",
+                film_distance,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn focus_too_close() {
+        let lens = LensSystem::parse(BICONVEX).unwrap();
+        assert!(lens.focus(0.05).is_none());
+        assert!(lens.focus(150.0).is_none());
+    }
+}
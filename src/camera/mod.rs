@@ -0,0 +1,29 @@
+mod aperture;
+mod cube_map;
+mod equirectangular;
+mod fisheye;
+mod lens_system;
+mod orthographic;
+mod perspective;
+mod realistic;
+mod stereo;
+
+pub use aperture::{Aperture, ApertureMask};
+pub use cube_map::CubeMap;
+pub use equirectangular::Equirectangular;
+pub use fisheye::{Fisheye, FisheyeProjection};
+pub use lens_system::LensSystem;
+pub use orthographic::Orthographic;
+pub use perspective::Perspective;
+pub use realistic::Realistic;
+pub use stereo::{Convergence, Stereo, StereoLayout};
+
+use crate::ray::Ray;
+
+/// Generates the rays seen through the image, for coordinates `s` and `t` going from `0` at the
+/// lower left to `1` at the upper right corner
+pub trait Camera: Send + Sync {
+    /// The ray through the image at `(s, t)`, or `None` if no light reaches the image there, like
+    /// where a lens blocks it
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray>;
+}
@@ -0,0 +1,80 @@
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+use super::Camera;
+
+/// A camera with parallel rays, where objects keep their size regardless of their distance, as in
+/// technical drawings and isometric views
+pub struct Orthographic {
+    direction: Vec3,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+}
+
+impl Orthographic {
+    /// `view_height` is the height of the visible part of the world, in world units
+    pub fn new(
+        look_from: Point3,
+        look_at: Point3,
+        v_up: Vec3,
+        view_height: f64,
+        aspect_ratio: f64,
+    ) -> Self {
+        let view_width = aspect_ratio * view_height;
+
+        let cw = (look_from - look_at).normalized();
+        let cu = v_up.cross(cw).normalized();
+        let cv = cw.cross(cu);
+
+        let horizontal = view_width * cu;
+        let vertical = view_height * cv;
+
+        // The rays start on the plane through `look_from`, so everything in front of it is seen
+        let lower_left_corner = look_from - horizontal / 2.0 - vertical / 2.0;
+
+        Self {
+            direction: (-1.0) * cw,
+            lower_left_corner,
+            horizontal,
+            vertical,
+        }
+    }
+}
+
+impl Camera for Orthographic {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        Some(Ray::new(
+            self.lower_left_corner + s * self.horizontal + t * self.vertical,
+            self.direction,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_rays_from_the_view_plane() {
+        let camera = Orthographic::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(1.0, 2.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            2.0,
+        );
+
+        for (s, t, origin) in [
+            (0.5, 0.5, Point3::new(1.0, 2.0, 3.0)),
+            (0.0, 0.0, Point3::new(-1.0, 1.0, 3.0)),
+            (1.0, 1.0, Point3::new(3.0, 3.0, 3.0)),
+            (1.0, 0.0, Point3::new(3.0, 1.0, 3.0)),
+        ] {
+            let r = camera.get_ray(s, t).unwrap();
+            assert!((r.origin() - origin).length() < 1.0e-12);
+            assert!((r.direction() - Vec3::new(0.0, 0.0, -1.0)).length() < 1.0e-12);
+        }
+    }
+}
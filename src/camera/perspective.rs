@@ -0,0 +1,361 @@
+use std::f64::consts::PI;
+
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+use super::{Aperture, Camera, Convergence};
+
+/// A pinhole camera, or a thin lens one with depth of field for a non-zero aperture
+pub struct Perspective {
+    origin: Point3,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    cu: Vec3,
+    cv: Vec3,
+    lens_radius: f64,
+    aperture: Aperture,
+    /// How far the lens barrel clips the aperture towards the edges of the image
+    vignetting: f64,
+    /// A point on the plane in focus, where the optical axis crosses it
+    focus_point: Point3,
+    /// Normal of the plane in focus, which faces the camera unless the lens is tilted
+    focus_normal: Vec3,
+}
+
+impl Perspective {
+    pub fn new(
+        look_from: Point3,
+        look_at: Point3,
+        v_up: Vec3,
+        v_fov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> Self {
+        // Vertical field-of-view in degrees
+        let theta = PI / 180.0 * v_fov;
+        let viewport_height = 2.0 * (theta / 2.0).tan();
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let cw = (look_from - look_at).normalized();
+        let cu = v_up.cross(cw).normalized();
+        let cv = cw.cross(cu);
+
+        let horizontal = focus_dist * viewport_width * cu;
+        let vertical = focus_dist * viewport_height * cv;
+
+        let lower_left_corner = look_from - horizontal / 2.0 - vertical / 2.0 - focus_dist * cw;
+
+        Self {
+            origin: look_from,
+            horizontal,
+            vertical,
+            lower_left_corner,
+            cu,
+            cv,
+            lens_radius: aperture / 2.0,
+            aperture: Aperture::Circle,
+            vignetting: 0.0,
+            focus_point: look_from - focus_dist * cw,
+            focus_normal: cw,
+        }
+    }
+
+    /// Shifts the lens parallel to the film by fractions of the image's width and height, which
+    /// moves the view without turning the camera, so lines that are parallel to the film stay
+    /// parallel in the image
+    pub fn with_shift(self, shift_x: f64, shift_y: f64) -> Perspective {
+        Perspective {
+            lower_left_corner: self.lower_left_corner
+                + shift_x * self.horizontal
+                + shift_y * self.vertical,
+            ..self
+        }
+    }
+
+    /// Tilts the lens to turn the plane in focus around the point where it crosses the optical
+    /// axis (the Scheimpflug principle): by `tilt` degrees around the horizontal axis, moving
+    /// its upper part away from the camera, and by `swing` degrees around the vertical axis,
+    /// moving its right part away
+    pub fn with_tilt(self, tilt: f64, swing: f64) -> Perspective {
+        let (tilt, swing) = (tilt.to_radians(), swing.to_radians());
+        let cw = self.cu.cross(self.cv);
+
+        let tilted = tilt.cos() * cw + tilt.sin() * self.cv;
+        let focus_normal = swing.cos() * tilted + swing.sin() * self.cu;
+
+        Perspective {
+            focus_normal: focus_normal.normalized(),
+            ..self
+        }
+    }
+
+    /// Changes the shape of the aperture from a circle, keeping its size
+    pub fn with_aperture(self, aperture: Aperture) -> Perspective {
+        Perspective { aperture, ..self }
+    }
+
+    /// Clips the aperture by the opening of the lens barrel, which moves off-center towards the
+    /// edges of the image and gives out of focus highlights there a cat's eye shape. At a
+    /// `vignetting` of 1 the opening is shifted by the lens radius at the sides of the image.
+    ///
+    /// Only the shape of the bokeh changes, not the brightness of the image.
+    pub fn with_vignetting(self, vignetting: f64) -> Perspective {
+        Perspective { vignetting, ..self }
+    }
+
+    /// A point on the lens relative to its center for the image position `(s, t)`
+    fn lens_sample(&self, s: f64, t: f64) -> Vec3 {
+        let shift = self.vignetting * Vec3::new(2.0 * s - 1.0, 2.0 * t - 1.0, 0.0);
+
+        // Rejection sample the part of the aperture inside the barrel's opening. When they hardly
+        // overlap, the last sample is moved to the nearest point of the opening instead, so that
+        // no light ever passes outside of the barrel.
+        let mut p = self.aperture.sample();
+        for _ in 0..64 {
+            if (p - shift).length() <= 1.0 {
+                return self.lens_radius * p;
+            }
+            p = self.aperture.sample();
+        }
+        if (p - shift).length() > 1.0 {
+            p = shift + (p - shift).normalized();
+        }
+
+        self.lens_radius * p
+    }
+
+    /// Moves the camera `offset` to the right to become one eye of a stereo pair, converging
+    /// with the other eye at the focus distance. A tilt of the lens turns with the eye when it's
+    /// toed in.
+    pub fn with_eye_offset(self, offset: f64, convergence: Convergence) -> Perspective {
+        let eye = self.origin + offset * self.cu;
+
+        match convergence {
+            // Keeping the image on the focus plane in place skews the frustum towards the center
+            Convergence::OffAxis => Perspective {
+                origin: eye,
+                ..self
+            },
+            Convergence::ToeIn => {
+                let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+                let scale = (eye - center).length() / (self.origin - center).length();
+
+                let cw = (eye - center).normalized();
+                let cu = self.cv.cross(cw).normalized();
+                let cv = cw.cross(cu);
+
+                // Turns a vector with the camera around the center of the image
+                let old_cw = self.cu.cross(self.cv);
+                let turn = |v: Vec3| v.dot(self.cu) * cu + v.dot(self.cv) * cv + v.dot(old_cw) * cw;
+
+                let horizontal = (scale * self.horizontal.length()) * cu;
+                let vertical = (scale * self.vertical.length()) * cv;
+
+                Perspective {
+                    origin: eye,
+                    lower_left_corner: center - horizontal / 2.0 - vertical / 2.0,
+                    horizontal,
+                    vertical,
+                    cu,
+                    cv,
+                    focus_point: center + turn(self.focus_point - center),
+                    focus_normal: turn(self.focus_normal),
+                    ..self
+                }
+            }
+        }
+    }
+}
+
+impl Camera for Perspective {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let rd = self.lens_sample(s, t);
+        let offset = self.cu * rd.x() + self.cv * rd.y();
+
+        // All rays of a pixel meet where the ray through the center of the lens crosses the plane
+        // in focus, or are parallel where a tilted plane doesn't cross it in front of the camera
+        let direction =
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin;
+        let distance = (self.focus_point - self.origin).dot(self.focus_normal)
+            / direction.dot(self.focus_normal);
+        if !(distance > 0.0 && distance.is_finite()) {
+            return Some(Ray::new(self.origin + offset, direction));
+        }
+
+        Some(Ray::new(
+            self.origin + offset,
+            distance * direction - offset,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_direction(actual: Vec3, expected: Vec3) {
+        assert!((actual.normalized() - expected.normalized()).length() < 1.0e-12);
+    }
+
+    #[test]
+    fn pinhole_rays_span_the_field_of_view() {
+        let camera = Perspective::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(1.0, 2.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            2.0,
+            0.0,
+            5.0,
+        );
+
+        for (s, t, expected) in [
+            (0.5, 0.5, Vec3::new(0.0, 0.0, -1.0)),
+            (1.0, 1.0, Vec3::new(2.0, 1.0, -1.0)),
+            (0.0, 0.5, Vec3::new(-2.0, 0.0, -1.0)),
+            (0.5, 0.0, Vec3::new(0.0, -1.0, -1.0)),
+        ] {
+            let r = camera.get_ray(s, t).unwrap();
+            assert!((r.origin() - Point3::new(1.0, 2.0, 3.0)).length() < 1.0e-12);
+            assert_direction(r.direction(), expected);
+        }
+    }
+
+    #[test]
+    fn lens_rays_meet_at_the_focus_distance() {
+        let look_from = Point3::new(0.0, 0.0, 0.0);
+        let camera = Perspective::new(
+            look_from,
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            2.0,
+            4.0,
+        );
+
+        for _ in 0..100 {
+            let r = camera.get_ray(0.5, 0.5).unwrap();
+            let offset = r.origin() - look_from;
+            assert!(offset.length() <= 1.0 + 1.0e-12 && offset.z().abs() < 1.0e-12);
+            assert!((r.at(1.0) - Point3::new(0.0, 0.0, -4.0)).length() < 1.0e-12);
+        }
+    }
+
+    fn eyes(convergence: Convergence) -> (Perspective, Perspective) {
+        let camera = || {
+            Perspective::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                60.0,
+                1.5,
+                0.0,
+                4.0,
+            )
+        };
+
+        (
+            camera().with_eye_offset(-0.2, convergence),
+            camera().with_eye_offset(0.2, convergence),
+        )
+    }
+
+    #[test]
+    fn off_axis_eyes_share_the_focus_plane() {
+        let (left, right) = eyes(Convergence::OffAxis);
+
+        for (s, t) in [(0.5, 0.5), (0.0, 0.0), (1.0, 0.25), (0.3, 0.9)] {
+            let (l, r) = (left.get_ray(s, t).unwrap(), right.get_ray(s, t).unwrap());
+            assert!((l.origin() - Point3::new(-0.2, 0.0, 0.0)).length() < 1.0e-12);
+            assert!((r.origin() - Point3::new(0.2, 0.0, 0.0)).length() < 1.0e-12);
+
+            // Without vertical parallax anywhere on the plane
+            assert!((l.at(1.0) - r.at(1.0)).length() < 1.0e-12);
+            assert!((l.at(1.0).z() + 4.0).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn toe_in_eyes_turn_towards_the_focus_point() {
+        let (left, right) = eyes(Convergence::ToeIn);
+
+        let (l, r) = (
+            left.get_ray(0.5, 0.5).unwrap(),
+            right.get_ray(0.5, 0.5).unwrap(),
+        );
+        assert!((l.at(1.0) - Point3::new(0.0, 0.0, -4.0)).length() < 1.0e-12);
+        assert!((r.at(1.0) - Point3::new(0.0, 0.0, -4.0)).length() < 1.0e-12);
+        assert_direction(l.direction(), Vec3::new(0.2, 0.0, -4.0));
+
+        // The rotated image planes see the corners at different heights on the focus plane
+        let height = |r: Ray| r.direction().y() * (-4.0 / r.direction().z());
+        let (l, r) = (
+            left.get_ray(0.0, 1.0).unwrap(),
+            right.get_ray(0.0, 1.0).unwrap(),
+        );
+        assert!((height(l) - height(r)).abs() > 1.0e-3);
+    }
+
+    fn camera() -> Perspective {
+        Perspective::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            0.5,
+            4.0,
+        )
+    }
+
+    #[test]
+    fn lens_samples_stay_in_the_barrel() {
+        // The opening doesn't overlap the aperture at all in the corners
+        let camera = camera().with_vignetting(3.0);
+        for _ in 0..100 {
+            let p = camera.lens_sample(1.0, 0.0);
+            let center = camera.lens_radius * Vec3::new(3.0, -3.0, 0.0);
+            assert!((p - center).length() <= camera.lens_radius * (1.0 + 1.0e-9));
+        }
+    }
+
+    #[test]
+    fn rays_through_the_image() {
+        // A pinhole without tilt or shift aims straight at the image on the focus plane
+        let camera = Perspective::new(
+            Point3::new(1.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.5,
+            0.0,
+            4.0,
+        );
+        for (s, t) in [(0.0, 0.0), (0.5, 0.5), (0.25, 0.9), (1.0, 1.0)] {
+            let r = camera.get_ray(s, t).unwrap();
+            let expected = camera.lower_left_corner + s * camera.horizontal + t * camera.vertical
+                - camera.origin;
+            assert_eq!((r.origin() - camera.origin).length(), 0.0);
+            assert!((r.direction() - expected).length() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn toe_in_keeps_the_tilt() {
+        let tilted = camera().with_tilt(20.0, 10.0);
+        let cw = tilted.cu.cross(tilted.cv);
+        let angle = tilted.focus_normal.dot(cw);
+
+        let eye = tilted.with_eye_offset(0.3, Convergence::ToeIn);
+        let eye_cw = eye.cu.cross(eye.cv);
+        assert!((eye.focus_normal.dot(eye_cw) - angle).abs() < 1.0e-12);
+        assert!((eye.focus_normal.length() - 1.0).abs() < 1.0e-12);
+
+        let untilted = camera().with_eye_offset(0.3, Convergence::ToeIn);
+        assert!((untilted.focus_normal - untilted.cu.cross(untilted.cv)).length() < 1.0e-12);
+    }
+}
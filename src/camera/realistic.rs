@@ -0,0 +1,117 @@
+use std::io::{self, ErrorKind};
+
+use rand::Rng;
+
+use crate::color::{WAVELENGTH_MAX, WAVELENGTH_MIN};
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+use super::lens_system::REFERENCE_WAVELENGTH;
+use super::{Camera, LensSystem};
+
+/// A camera that traces rays through the elements of a compound lens, showing its distortion,
+/// vignetting and, for lenses with dispersive glasses, chromatic aberration.
+///
+/// World units are taken to be meters, while the lens and the film are measured in millimeters.
+pub struct Realistic {
+    /// The center of the film
+    origin: Point3,
+    cu: Vec3,
+    cv: Vec3,
+    cw: Vec3,
+    lens: LensSystem,
+    /// Whether the lens splits the light up by wavelength
+    is_dispersive: bool,
+    /// Distance between the film and the rear element, in millimeters
+    film_distance: f64,
+    film_width: f64,
+    film_height: f64,
+}
+
+impl Realistic {
+    /// `film_diagonal` is the diagonal of the film in millimeters, like 43.3 for full frame
+    /// 35mm, and the lens is focused on objects at `focus_dist` from the film. Fails if the lens
+    /// can't focus that close.
+    pub fn new(
+        look_from: Point3,
+        look_at: Point3,
+        v_up: Vec3,
+        lens: LensSystem,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+        focus_dist: f64,
+    ) -> io::Result<Self> {
+        let cw = (look_from - look_at).normalized();
+        let cu = v_up.cross(cw).normalized();
+        let cv = cw.cross(cu);
+
+        let film_height = film_diagonal / (1.0 + aspect_ratio.powi(2)).sqrt();
+        let film_width = aspect_ratio * film_height;
+        let film_distance = lens.focus(1000.0 * focus_dist).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The lens can't focus at {} m", focus_dist),
+            )
+        })?;
+
+        Ok(Self {
+            origin: look_from,
+            cu,
+            cv,
+            cw,
+            is_dispersive: lens.is_dispersive(),
+            lens,
+            film_distance,
+            film_width,
+            film_height,
+        })
+    }
+
+    /// Converts a vector in the lens' frame to the world
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        v.x() * self.cu + v.y() * self.cv - v.z() * self.cw
+    }
+
+    /// Converts a point in the lens' frame in millimeters to the world
+    fn point_to_world(&self, p: Vec3) -> Point3 {
+        self.origin + 0.001 * self.to_world(p)
+    }
+}
+
+impl Camera for Realistic {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let mut rng = rand::thread_rng();
+
+        // The lens turns the image upside down
+        let film_point = Vec3::new(
+            (0.5 - s) * self.film_width,
+            (0.5 - t) * self.film_height,
+            0.0,
+        );
+
+        // Only dispersive lenses split the light up by wavelength
+        let wavelength = if self.is_dispersive {
+            Some(rng.gen_range(WAVELENGTH_MIN..WAVELENGTH_MAX))
+        } else {
+            None
+        };
+
+        // Aim at a random point on the rear element, the light the lens blocks on the way being
+        // lost like in a real camera
+        let rear = self.lens.rear_aperture_radius() * Vec3::random_in_unit_disk();
+        let target = Vec3::new(rear.x(), rear.y(), self.film_distance);
+        let (origin, direction) = self.lens.trace(
+            self.film_distance,
+            film_point,
+            target - film_point,
+            wavelength.unwrap_or(REFERENCE_WAVELENGTH),
+        )?;
+
+        Some(Ray::with_wavelength(
+            self.point_to_world(origin),
+            self.to_world(direction),
+            wavelength,
+        ))
+    }
+}
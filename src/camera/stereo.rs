@@ -0,0 +1,91 @@
+use crate::ray::Ray;
+
+use super::Camera;
+
+/// How the two eyes of a stereo pair are aimed at the point where they converge
+#[derive(Clone, Copy)]
+pub enum Convergence {
+    /// Parallel eyes with frustums skewed towards each other, without vertical parallax
+    OffAxis,
+    /// Both eyes rotated towards the point, which is simpler but distorts towards the edges
+    ToeIn,
+}
+
+/// Where the images of the two eyes go in the output
+#[derive(Clone, Copy)]
+pub enum StereoLayout {
+    /// The left eye on the left half, the right eye on the right half
+    SideBySide,
+    /// The left eye on the top half, the right eye on the bottom half
+    TopBottom,
+}
+
+/// Renders the views of two eyes next to each other in one image
+pub struct Stereo {
+    left: Box<dyn Camera>,
+    right: Box<dyn Camera>,
+    layout: StereoLayout,
+}
+
+impl Stereo {
+    pub fn new(left: Box<dyn Camera>, right: Box<dyn Camera>, layout: StereoLayout) -> Self {
+        Self {
+            left,
+            right,
+            layout,
+        }
+    }
+}
+
+impl Camera for Stereo {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => self.left.get_ray(2.0 * s, t),
+            StereoLayout::SideBySide => self.right.get_ray(2.0 * s - 1.0, t),
+            StereoLayout::TopBottom if t >= 0.5 => self.left.get_ray(s, 2.0 * t - 1.0),
+            StereoLayout::TopBottom => self.right.get_ray(s, 2.0 * t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Orthographic;
+    use crate::point3::Point3;
+    use crate::vec3::Vec3;
+
+    /// An eye whose rays start around `x` on the x-axis
+    fn eye(x: f64) -> Box<dyn Camera> {
+        Box::new(Orthographic::new(
+            Point3::new(x, 0.0, 0.0),
+            Point3::new(x, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            2.0,
+            1.0,
+        ))
+    }
+
+    #[test]
+    fn eyes_fill_their_halves() {
+        let side_by_side = Stereo::new(eye(-10.0), eye(10.0), StereoLayout::SideBySide);
+        for (s, t, origin) in [
+            (0.25, 0.5, Point3::new(-10.0, 0.0, 0.0)),
+            (0.0, 0.0, Point3::new(-11.0, -1.0, 0.0)),
+            (0.75, 0.5, Point3::new(10.0, 0.0, 0.0)),
+            (1.0, 1.0, Point3::new(11.0, 1.0, 0.0)),
+        ] {
+            assert!((side_by_side.get_ray(s, t).unwrap().origin() - origin).length() < 1.0e-12);
+        }
+
+        let top_bottom = Stereo::new(eye(-10.0), eye(10.0), StereoLayout::TopBottom);
+        for (s, t, origin) in [
+            (0.5, 0.75, Point3::new(-10.0, 0.0, 0.0)),
+            (1.0, 1.0, Point3::new(-9.0, 1.0, 0.0)),
+            (0.5, 0.25, Point3::new(10.0, 0.0, 0.0)),
+            (0.0, 0.0, Point3::new(9.0, -1.0, 0.0)),
+        ] {
+            assert!((top_bottom.get_ray(s, t).unwrap().origin() - origin).length() < 1.0e-12);
+        }
+    }
+}
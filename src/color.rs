@@ -1,7 +1,15 @@
-use std::ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Range};
+use std::{
+    ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Range},
+    sync::OnceLock,
+};
 
 use rand::Rng;
 
+/// The shortest wavelength (in nanometers) considered visible
+pub const WAVELENGTH_MIN: f64 = 380.0;
+/// The longest wavelength (in nanometers) considered visible
+pub const WAVELENGTH_MAX: f64 = 780.0;
+
 #[derive(Clone, Copy)]
 pub struct Color {
     e: [f64; 3],
@@ -40,6 +48,67 @@ impl Color {
             ],
         }
     }
+
+    pub fn is_black(self) -> bool {
+        self[0] <= 0.0 && self[1] <= 0.0 && self[2] <= 0.0
+    }
+
+    /// The relative luminance of a linear sRGB color
+    pub fn luminance(self) -> f64 {
+        0.2126 * self[0] + 0.7152 * self[1] + 0.0722 * self[2]
+    }
+
+    pub fn exp(self) -> Color {
+        Color {
+            e: [self[0].exp(), self[1].exp(), self[2].exp()],
+        }
+    }
+
+    /// The weight a single wavelength contributes to each RGB channel, scaled such that the
+    /// average over uniformly sampled visible wavelengths is white
+    pub fn from_wavelength(wavelength: f64) -> Color {
+        static NORMALIZATION: OnceLock<Color> = OnceLock::new();
+
+        let normalization = NORMALIZATION.get_or_init(|| {
+            let steps = (WAVELENGTH_MAX - WAVELENGTH_MIN) as u64;
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            for i in 0..steps {
+                sum += Self::wavelength_to_linear_srgb(WAVELENGTH_MIN + (i as f64) + 0.5);
+            }
+
+            Color::new(
+                (steps as f64) / sum[0],
+                (steps as f64) / sum[1],
+                (steps as f64) / sum[2],
+            )
+        });
+
+        *normalization * Self::wavelength_to_linear_srgb(wavelength)
+    }
+
+    fn wavelength_to_linear_srgb(wavelength: f64) -> Color {
+        // Multi-lobe Gaussian fit of the CIE 1931 color matching functions (Wyman et al. 2013)
+        let g = |mu: f64, sigma_low: f64, sigma_high: f64| {
+            let sigma = if wavelength < mu {
+                sigma_low
+            } else {
+                sigma_high
+            };
+            (-0.5 * ((wavelength - mu) / sigma).powi(2)).exp()
+        };
+
+        let x = 1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7)
+            - 0.065 * g(501.1, 20.4, 26.2);
+        let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+        let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+
+        // Colors outside of the sRGB gamut are clamped
+        Color::new(
+            (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0),
+            (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0),
+            (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0),
+        )
+    }
 }
 
 impl Index<usize> for Color {
@@ -85,7 +154,7 @@ impl Mul<f64> for Color {
 }
 
 impl MulAssign<f64> for Color {
-    fn mul_assign(&mut self, other: f64) -> () {
+    fn mul_assign(&mut self, other: f64) {
         *self = Color {
             e: [self[0] * other, self[1] * other, self[2] * other],
         };
@@ -111,3 +180,28 @@ impl Mul<Color> for Color {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wavelengths_average_to_white() {
+        let steps = 10_000;
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        for i in 0..steps {
+            let t = ((i as f64) + 0.5) / (steps as f64);
+            sum += Color::from_wavelength(WAVELENGTH_MIN + t * (WAVELENGTH_MAX - WAVELENGTH_MIN));
+        }
+
+        for channel in 0..3 {
+            let average = sum[channel] / (steps as f64);
+            assert!(
+                (average - 1.0).abs() < 1.0e-3,
+                "channel {}: {}",
+                channel,
+                average
+            );
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::point3::Point3;
+use crate::texture::Perlin;
+
+/// Densities sampled on a regular 3D grid, filling the unit cube
+pub struct DensityGrid {
+    resolution: [usize; 3],
+    /// Voxels with x varying fastest, then y, then z
+    voxels: Vec<f64>,
+    max_density: f64,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], voxels: Vec<f64>) -> DensityGrid {
+        assert!(resolution.iter().all(|&n| n > 0), "Grid without voxels");
+        assert_eq!(
+            voxels.len(),
+            resolution[0] * resolution[1] * resolution[2],
+            "Grid resolution doesn't match its voxels"
+        );
+
+        let max_density = voxels.iter().cloned().fold(0.0, f64::max);
+
+        DensityGrid {
+            resolution,
+            voxels,
+            max_density,
+        }
+    }
+
+    /// Loads a raw voxel file, consisting of the resolution as three little-endian `u32`
+    /// followed by the densities as little-endian `f32`
+    pub fn load_raw(path: impl AsRef<Path>) -> io::Result<DensityGrid> {
+        DensityGrid::parse_raw(&fs::read(path)?)
+    }
+
+    /// Parses the contents of a raw voxel file
+    pub fn parse_raw(data: &[u8]) -> io::Result<DensityGrid> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let words: Vec<[u8; 4]> = data
+            .chunks_exact(4)
+            .map(|word| [word[0], word[1], word[2], word[3]])
+            .collect();
+        if words.len() < 3 {
+            return Err(invalid("Truncated voxel header"));
+        }
+
+        let resolution = [
+            u32::from_le_bytes(words[0]) as usize,
+            u32::from_le_bytes(words[1]) as usize,
+            u32::from_le_bytes(words[2]) as usize,
+        ];
+        if resolution.contains(&0) {
+            return Err(invalid("Grid without voxels"));
+        }
+        let voxel_count = resolution[0]
+            .checked_mul(resolution[1])
+            .and_then(|count| count.checked_mul(resolution[2]))
+            .ok_or_else(|| invalid("Grid too large"))?;
+        if words.len() - 3 < voxel_count {
+            return Err(invalid("Truncated voxel data"));
+        }
+
+        let voxels = words[3..3 + voxel_count]
+            .iter()
+            .map(|&word| f32::from_le_bytes(word).max(0.0) as f64)
+            .collect();
+
+        Ok(DensityGrid::new(resolution, voxels))
+    }
+
+    /// A puffy cloud of turbulent noise that fades out towards the sides of the grid
+    pub fn cloud(resolution: usize, frequency: f64) -> DensityGrid {
+        let perlin = Perlin::new();
+        let mut voxels = Vec::with_capacity(resolution.pow(3));
+
+        for k in 0..resolution {
+            for j in 0..resolution {
+                for i in 0..resolution {
+                    let to_unit = |i: usize| ((i as f64) + 0.5) / (resolution as f64);
+                    let (x, y, z) = (to_unit(i), to_unit(j), to_unit(k));
+
+                    let radius = ((x - 0.5).powi(2) + (y - 0.5).powi(2) + (z - 0.5).powi(2)).sqrt();
+                    let falloff = (1.0 - 2.0 * radius).max(0.0);
+                    let noise = perlin
+                        .turbulence(Point3::new(frequency * x, frequency * y, frequency * z), 5);
+
+                    voxels.push((2.0 * falloff * noise).min(1.0));
+                }
+            }
+        }
+
+        DensityGrid::new([resolution, resolution, resolution], voxels)
+    }
+
+    pub fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    /// Trilinearly interpolated density at a point of the unit cube
+    pub fn density(&self, p: Point3) -> f64 {
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            // Voxel centers sit at half-integer positions
+            let x = (p[axis] * (self.resolution[axis] as f64) - 0.5)
+                .clamp(0.0, (self.resolution[axis] - 1) as f64);
+            base[axis] = (x as usize).min(self.resolution[axis].saturating_sub(2));
+            frac[axis] = x - (base[axis] as f64);
+        }
+
+        let voxel = |i: usize, j: usize, k: usize| {
+            let i = i.min(self.resolution[0] - 1);
+            let j = j.min(self.resolution[1] - 1);
+            let k = k.min(self.resolution[2] - 1);
+            self.voxels[(k * self.resolution[1] + j) * self.resolution[0] + i]
+        };
+
+        let mut density = 0.0;
+        for dk in 0..2 {
+            for dj in 0..2 {
+                for di in 0..2 {
+                    let weight = |d: usize, frac: f64| if d == 1 { frac } else { 1.0 - frac };
+                    density += weight(di, frac[0])
+                        * weight(dj, frac[1])
+                        * weight(dk, frac[2])
+                        * voxel(base[0] + di, base[1] + dj, base[2] + dk);
+                }
+            }
+        }
+
+        density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(resolution: [u32; 3], voxels: &[f32]) -> Vec<u8> {
+        resolution
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .chain(voxels.iter().flat_map(|v| v.to_le_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let voxels: Vec<f32> = (0..24).map(|i| (i as f32) / 8.0).collect();
+        let grid = DensityGrid::parse_raw(&encode([2, 3, 4], &voxels)).unwrap();
+
+        assert_eq!(grid.resolution, [2, 3, 4]);
+        assert_eq!(
+            grid.voxels,
+            voxels.iter().map(|&v| v as f64).collect::<Vec<_>>()
+        );
+        assert_eq!(grid.max_density(), 23.0 / 8.0);
+    }
+
+    #[test]
+    fn raw_invalid() {
+        let error = |data: &[u8]| DensityGrid::parse_raw(data).err().unwrap().kind();
+
+        assert_eq!(error(&encode([2, 2, 2], &[1.0; 7])), ErrorKind::InvalidData);
+        assert_eq!(error(&encode([0, 2, 2], &[])), ErrorKind::InvalidData);
+        assert_eq!(
+            error(&encode([u32::MAX, u32::MAX, u32::MAX], &[])),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(error(&[0, 0, 0, 1]), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,561 @@
+use std::io::{self, ErrorKind};
+
+use crate::color::Color;
+
+use super::inflate::inflate_zlib;
+
+/// The first four bytes of every OpenEXR file
+pub const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn unsupported(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::Unsupported, message.to_string())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PixelType {
+    Uint,
+    Half,
+    Float,
+}
+
+impl PixelType {
+    fn size(self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::Uint | PixelType::Float => 4,
+        }
+    }
+}
+
+struct Channel {
+    name: String,
+    pixel_type: PixelType,
+}
+
+/// Reads little-endian values from a byte slice
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(count)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("Truncated OpenEXR image"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    /// A null-terminated string
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.data[self.pos.min(self.data.len())..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid("Truncated OpenEXR header"))?;
+        let string = String::from_utf8_lossy(self.bytes(length)?).into_owned();
+        self.pos += 1;
+
+        Ok(string)
+    }
+}
+
+/// Decodes a single-part scanline OpenEXR image with uncompressed, RLE or ZIP compressed
+/// blocks, reading the R, G and B channels or a luminance-only Y channel
+pub fn read_exr(data: &[u8]) -> io::Result<(usize, usize, Vec<Color>)> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.bytes(4)? != MAGIC {
+        return Err(invalid("Not an OpenEXR image"));
+    }
+    let version = reader.i32()?;
+    if version & 0xff != 2 {
+        return Err(unsupported("Unsupported OpenEXR version"));
+    }
+    if version & 0x1a00 != 0 {
+        return Err(unsupported(
+            "Only single-part scanline OpenEXR images are supported",
+        ));
+    }
+
+    // The header is a list of attributes ending with an empty name
+    let mut channels = None;
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _type = reader.string()?;
+        let size = usize::try_from(reader.i32()?).map_err(|_| invalid("Invalid attribute"))?;
+        let mut value = Reader {
+            data: reader.bytes(size)?,
+            pos: 0,
+        };
+
+        match name.as_str() {
+            "channels" => {
+                let mut list = Vec::new();
+                loop {
+                    let name = value.string()?;
+                    if name.is_empty() {
+                        break;
+                    }
+                    let pixel_type = match value.i32()? {
+                        0 => PixelType::Uint,
+                        1 => PixelType::Half,
+                        2 => PixelType::Float,
+                        _ => return Err(invalid("Invalid OpenEXR pixel type")),
+                    };
+                    let _linear_and_reserved = value.bytes(4)?;
+                    if value.i32()? != 1 || value.i32()? != 1 {
+                        return Err(unsupported("Subsampled OpenEXR channels aren't supported"));
+                    }
+                    list.push(Channel { name, pixel_type });
+                }
+                channels = Some(list);
+            }
+            "compression" => compression = Some(value.u8()?),
+            "dataWindow" => {
+                data_window = Some([value.i32()?, value.i32()?, value.i32()?, value.i32()?])
+            }
+            _ => {}
+        }
+    }
+
+    let channels = channels.ok_or_else(|| invalid("Missing OpenEXR channels"))?;
+    let [x_min, y_min, x_max, y_max] =
+        data_window.ok_or_else(|| invalid("Missing OpenEXR data window"))?;
+    let extent = |min: i32, max: i32| {
+        usize::try_from(max as i64 - min as i64 + 1)
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| invalid("Empty OpenEXR image"))
+    };
+    let (width, height) = (extent(x_min, x_max)?, extent(y_min, y_max)?);
+
+    let compression = compression.ok_or_else(|| invalid("Missing OpenEXR compression"))?;
+    let lines_per_block = match compression {
+        0..=2 => 1,
+        3 => 16,
+        _ => {
+            return Err(unsupported(
+                "Only uncompressed, RLE and ZIP compressed OpenEXR images are supported",
+            ))
+        }
+    };
+
+    // Pick the color channels, which may be in any order
+    let find = |name: &str| channels.iter().position(|channel| channel.name == name);
+    let rgb = match (find("R"), find("G"), find("B"), find("Y")) {
+        (Some(r), Some(g), Some(b), _) => [r, g, b],
+        (_, _, _, Some(y)) => [y, y, y],
+        _ => return Err(unsupported("OpenEXR image without RGB or Y channels")),
+    };
+
+    let bytes_per_pixel: usize = channels.iter().map(|c| c.pixel_type.size()).sum();
+    let line_size = width
+        .checked_mul(bytes_per_pixel)
+        .ok_or_else(|| invalid("OpenEXR image too large"))?;
+    let pixel_count = width
+        .checked_mul(height)
+        .ok_or_else(|| invalid("OpenEXR image too large"))?;
+
+    // The offsets of the blocks follow the header, but the blocks themselves say which lines they
+    // hold, so reading them in order is enough
+    let block_count = height.div_ceil(lines_per_block);
+    for _ in 0..block_count {
+        reader.u64()?;
+    }
+
+    let mut pixels = vec![Color::new(0.0, 0.0, 0.0); pixel_count];
+    for _ in 0..block_count {
+        let y = reader.i32()? as i64 - y_min as i64;
+        let size = usize::try_from(reader.i32()?).map_err(|_| invalid("Invalid block size"))?;
+        let packed = reader.bytes(size)?;
+
+        let first = usize::try_from(y)
+            .ok()
+            .filter(|&y| y < height && y % lines_per_block == 0)
+            .ok_or_else(|| invalid("Invalid OpenEXR block position"))?;
+        let lines = lines_per_block.min(height - first);
+        let expected = lines * line_size;
+
+        // Blocks that wouldn't get any smaller are stored uncompressed
+        let block = if compression == 0 || size == expected {
+            packed.to_vec()
+        } else if compression == 1 {
+            reconstruct(decode_rle(packed, expected)?)
+        } else {
+            reconstruct(inflate_zlib(packed, expected)?)
+        };
+        if block.len() != expected {
+            return Err(invalid("Invalid OpenEXR block size"));
+        }
+
+        // Each line holds all values of one channel after the other
+        for (line, values) in block.chunks_exact(line_size).enumerate() {
+            let mut starts = Vec::with_capacity(channels.len());
+            let mut start = 0;
+            for channel in &channels {
+                starts.push(start);
+                start += width * channel.pixel_type.size();
+            }
+
+            let row = &mut pixels[(first + line) * width..(first + line + 1) * width];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let value = |index: usize| {
+                    let pixel_type = channels[index].pixel_type;
+                    let offset = starts[index] + x * pixel_type.size();
+                    sample(pixel_type, &values[offset..offset + pixel_type.size()])
+                };
+                *pixel = Color::new(value(rgb[0]), value(rgb[1]), value(rgb[2]));
+            }
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// Converts a stored value to a number
+fn sample(pixel_type: PixelType, bytes: &[u8]) -> f64 {
+    match pixel_type {
+        PixelType::Uint => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        PixelType::Half => half_to_f64(u16::from_le_bytes(bytes.try_into().unwrap())),
+        PixelType::Float => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+    }
+}
+
+/// Converts an IEEE 754 half precision float
+fn half_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f64;
+
+    match exponent {
+        0 => sign * mantissa * 2.0_f64.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2.0_f64.powi(exponent - 15),
+    }
+}
+
+/// Expands OpenEXR's run-length encoding, where a negative count is followed by that many
+/// literal bytes and any other count by a byte repeated one more time than the count
+fn decode_rle(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(size);
+    let mut pos = 0;
+    while pos < data.len() {
+        let count = data[pos] as i8;
+        pos += 1;
+
+        if count < 0 {
+            let count = -(count as isize) as usize;
+            let literals = data
+                .get(pos..pos + count)
+                .ok_or_else(|| invalid("Truncated OpenEXR run"))?;
+            output.extend_from_slice(literals);
+            pos += count;
+        } else {
+            let value = *data
+                .get(pos)
+                .ok_or_else(|| invalid("Truncated OpenEXR run"))?;
+            output.resize(output.len() + count as usize + 1, value);
+            pos += 1;
+        }
+
+        if output.len() > size {
+            return Err(invalid("Overlong OpenEXR run"));
+        }
+    }
+
+    Ok(output)
+}
+
+/// Undoes the transformation applied before RLE and ZIP compression: the bytes are stored as
+/// differences to the previous one, with the even and then the odd bytes of the original data
+/// stored in two halves
+fn reconstruct(mut data: Vec<u8>) -> Vec<u8> {
+    for i in 1..data.len() {
+        data[i] = data[i - 1].wrapping_add(data[i]).wrapping_sub(128);
+    }
+
+    let (even, odd) = data.split_at(data.len().div_ceil(2));
+    let mut output = Vec::with_capacity(data.len());
+    for (i, &value) in even.iter().enumerate() {
+        output.push(value);
+        if let Some(&value) = odd.get(i) {
+            output.push(value);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u16 = 0x3c00;
+    const HALF: u16 = 0x3800;
+    const TWO: u16 = 0x4000;
+
+    fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+        for string in [name, kind] {
+            header.extend_from_slice(string.as_bytes());
+            header.push(0);
+        }
+        header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        header.extend_from_slice(value);
+    }
+
+    /// An image with HALF channels, given as the lines of each block with the values of the
+    /// channels one after the other, compressed by `compress`
+    fn image(
+        names: &[&str],
+        compression: u8,
+        (width, height): (i32, i32),
+        blocks: &[Vec<u16>],
+        compress: impl Fn(Vec<u8>) -> Vec<u8>,
+    ) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&2i32.to_le_bytes());
+
+        let mut channels = Vec::new();
+        for name in names {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            for value in [1i32, 0, 1, 1] {
+                channels.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        channels.push(0);
+        attribute(&mut data, "channels", "chlist", &channels);
+        attribute(&mut data, "compression", "compression", &[compression]);
+        let window: Vec<u8> = [0, 10, width - 1, height + 9]
+            .iter()
+            .flat_map(|value: &i32| value.to_le_bytes())
+            .collect();
+        attribute(&mut data, "dataWindow", "box2i", &window);
+        data.push(0);
+
+        data.extend(std::iter::repeat_n(0, 8 * blocks.len()));
+        let lines_per_block = height as usize / blocks.len().max(1);
+        for (index, block) in blocks.iter().enumerate() {
+            let bytes = compress(block.iter().flat_map(|value| value.to_le_bytes()).collect());
+            data.extend_from_slice(&(10 + (index * lines_per_block) as i32).to_le_bytes());
+            data.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
+            data.extend_from_slice(&bytes);
+        }
+
+        data
+    }
+
+    /// The inverse of `reconstruct`
+    fn predict(data: Vec<u8>) -> Vec<u8> {
+        let interleaved: Vec<u8> = data
+            .iter()
+            .step_by(2)
+            .chain(data.iter().skip(1).step_by(2))
+            .copied()
+            .collect();
+
+        let mut output = interleaved.clone();
+        for i in 1..output.len() {
+            output[i] = interleaved[i]
+                .wrapping_sub(interleaved[i - 1])
+                .wrapping_add(128);
+        }
+
+        output
+    }
+
+    fn rle(data: Vec<u8>) -> Vec<u8> {
+        let data = predict(data);
+        let mut output = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let run = data[pos..]
+                .iter()
+                .take(128)
+                .take_while(|&&b| b == data[pos])
+                .count();
+            if run > 1 {
+                output.extend_from_slice(&[(run - 1) as u8, data[pos]]);
+                pos += run;
+            } else {
+                output.extend_from_slice(&[(-1i8) as u8, data[pos]]);
+                pos += 1;
+            }
+        }
+
+        output
+    }
+
+    /// Wraps the data in a zlib stream of a single stored block
+    fn zip(data: Vec<u8>) -> Vec<u8> {
+        let data = predict(data);
+        let length = data.len() as u16;
+        let mut output = vec![0x78, 0x01, 0x01];
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&(!length).to_le_bytes());
+        output.extend_from_slice(&data);
+        output.extend_from_slice(&[0; 4]);
+
+        output
+    }
+
+    fn assert_image(data: &[u8], size: (usize, usize), expected: Vec<Color>) {
+        let (width, height, pixels) = read_exr(data).unwrap();
+        assert_eq!((width, height), size);
+        assert_eq!(pixels.len(), expected.len());
+        for (pixel, expected) in pixels.iter().zip(expected) {
+            for channel in 0..3 {
+                assert_eq!(pixel[channel], expected[channel]);
+            }
+        }
+    }
+
+    fn rgb_lines() -> Vec<Vec<u16>> {
+        // Two lines of two pixels, with the channels stored in alphabetical order
+        vec![
+            vec![HALF, HALF, ONE, ONE, TWO, 0],
+            vec![0, ONE, 0, 0, ONE, ONE],
+        ]
+    }
+
+    fn expected_rgb() -> Vec<Color> {
+        vec![
+            Color::new(2.0, 1.0, 0.5),
+            Color::new(0.0, 1.0, 0.5),
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(1.0, 0.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn uncompressed() {
+        let data = image(&["B", "G", "R"], 0, (2, 2), &rgb_lines(), |b| b);
+        assert_image(&data, (2, 2), expected_rgb());
+    }
+
+    #[test]
+    fn run_length_encoded() {
+        let data = image(&["B", "G", "R"], 1, (2, 2), &rgb_lines(), rle);
+        assert_image(&data, (2, 2), expected_rgb());
+    }
+
+    #[test]
+    fn zip_compressed() {
+        // Up to 16 lines per block
+        let block = rgb_lines().concat();
+        let data = image(&["B", "G", "R"], 3, (2, 2), &[block], zip);
+        assert_image(&data, (2, 2), expected_rgb());
+
+        let data = image(&["B", "G", "R"], 2, (2, 2), &rgb_lines(), zip);
+        assert_image(&data, (2, 2), expected_rgb());
+    }
+
+    #[test]
+    fn zip_compressed_with_huffman_codes() {
+        // Eight lines of eight pixels, predicted and compressed by zlib with its fixed and its
+        // dynamic Huffman codes
+        const FIXED: [u8; 68] = [
+            0x78, 0x01, 0x63, 0x68, 0x18, 0xe2, 0x60, 0x87, 0xc7, 0x9e, 0x06, 0x97, 0x1d, 0x1e,
+            0x3b, 0x5a, 0x6a, 0x3a, 0x1a, 0x2a, 0x5a, 0x6a, 0x5a, 0x5a, 0x6a, 0x5c, 0x1a, 0xf6,
+            0x00, 0x69, 0x87, 0x86, 0x1d, 0x0d, 0x1e, 0x0d, 0x07, 0x1c, 0x76, 0x34, 0xb4, 0x34,
+            0xd4, 0x34, 0x00, 0xe5, 0x1b, 0x80, 0xe2, 0x0d, 0x35, 0x2d, 0x2e, 0x07, 0x2a, 0x1a,
+            0x3a, 0x1c, 0xf6, 0xb8, 0x0c, 0x12, 0x7d, 0x00, 0xc9, 0xd1, 0xbf, 0xbd,
+        ];
+        const DYNAMIC: [u8; 65] = [
+            0x78, 0xda, 0xd5, 0x8b, 0xa1, 0x11, 0x00, 0x31, 0x0c, 0xc3, 0x3a, 0x5a, 0x40, 0x40,
+            0x46, 0x2a, 0x08, 0x10, 0x30, 0xe8, 0x48, 0x01, 0x01, 0x1d, 0xed, 0x33, 0x46, 0xdf,
+            0x44, 0x77, 0xd2, 0x79, 0xf1, 0xf3, 0x55, 0x34, 0x5e, 0x51, 0xca, 0xc3, 0x56, 0x4a,
+            0xe9, 0xf4, 0xd0, 0x28, 0x82, 0x6b, 0x85, 0x48, 0xa6, 0x33, 0x9e, 0x94, 0xdf, 0xcd,
+            0xb1, 0xf6, 0x47, 0x7e, 0x1f, 0xc9, 0xd1, 0xbf, 0xbd,
+        ];
+        assert_eq!((FIXED[2] >> 1) & 3, 1);
+        assert_eq!((DYNAMIC[2] >> 1) & 3, 2);
+
+        let block: Vec<u16> = (0..8)
+            .flat_map(|y| (0..3).flat_map(move |c| (0..8).map(move |x| (x, y, c))))
+            .map(|(x, y, c)| [0, HALF, ONE, TWO][(x * x + 3 * y + c) % 7 % 4])
+            .collect();
+        // The same lines uncompressed, one per block
+        let lines: Vec<Vec<u16>> = block.chunks(3 * 8).map(<[u16]>::to_vec).collect();
+        let (_, _, expected) =
+            read_exr(&image(&["B", "G", "R"], 0, (8, 8), &lines, |b| b)).unwrap();
+
+        for compressed in [&FIXED[..], &DYNAMIC[..]] {
+            let data = image(
+                &["B", "G", "R"],
+                3,
+                (8, 8),
+                std::slice::from_ref(&block),
+                |_| compressed.to_vec(),
+            );
+            assert_image(&data, (8, 8), expected.clone());
+        }
+    }
+
+    #[test]
+    fn luminance() {
+        let data = image(&["Y"], 0, (2, 1), &[vec![HALF, TWO]], |b| b);
+        let expected = vec![Color::new(0.5, 0.5, 0.5), Color::new(2.0, 2.0, 2.0)];
+        assert_image(&data, (2, 1), expected);
+    }
+
+    #[test]
+    fn unsupported_images() {
+        let kind = |data: &[u8]| read_exr(data).err().map(|e| e.kind());
+
+        let mut tiled = image(&["Y"], 0, (1, 1), &[vec![ONE]], |b| b);
+        tiled[5] = 0x02;
+        assert_eq!(kind(&tiled), Some(ErrorKind::Unsupported));
+
+        let piz = image(&["Y"], 4, (1, 1), &[vec![ONE]], |b| b);
+        assert_eq!(kind(&piz), Some(ErrorKind::Unsupported));
+
+        let alpha = image(&["A"], 0, (1, 1), &[vec![ONE]], |b| b);
+        assert_eq!(kind(&alpha), Some(ErrorKind::Unsupported));
+
+        let valid = image(&["Y"], 0, (2, 1), &[vec![ONE, ONE]], |b| b);
+        assert_eq!(
+            kind(&valid[..valid.len() - 1]),
+            Some(ErrorKind::InvalidData)
+        );
+        assert_eq!(kind(b"#?RADIANCE\n"), Some(ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(half_to_f64(0x0000), 0.0);
+        assert_eq!(half_to_f64(0x3c00), 1.0);
+        assert_eq!(half_to_f64(0xc000), -2.0);
+        assert_eq!(half_to_f64(0x3555), 0.333251953125);
+        assert_eq!(half_to_f64(0x7bff), 65504.0);
+        assert_eq!(half_to_f64(0x0001), 2.0_f64.powi(-24));
+        assert_eq!(half_to_f64(0x7c00), f64::INFINITY);
+        assert!(half_to_f64(0x7e00).is_nan());
+    }
+}
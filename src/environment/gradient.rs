@@ -0,0 +1,22 @@
+use crate::{color::Color, vec3::Vec3};
+
+use super::Environment;
+
+/// A sky blending linearly from one color straight down to another straight up
+pub struct Gradient {
+    bottom: Color,
+    top: Color,
+}
+
+impl Gradient {
+    pub fn new(bottom: Color, top: Color) -> Gradient {
+        Gradient { bottom, top }
+    }
+}
+
+impl Environment for Gradient {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let t = 0.5 * (direction.y() + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
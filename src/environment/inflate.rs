@@ -0,0 +1,334 @@
+use std::io::{self, ErrorKind};
+
+/// Base lengths and extra bits of the length symbols 257 to 285
+const LENGTH_BASE: [usize; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances and extra bits of the distance symbols
+const DISTANCE_BASE: [usize; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which the lengths of the code length code are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// Decompresses zlib data (RFC 1950 and 1951), such as the ZIP compressed blocks of OpenEXR
+/// images, expecting exactly `size` bytes
+pub fn inflate_zlib(data: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    if data.len() < 6 {
+        return Err(invalid("Truncated zlib stream"));
+    }
+    let (method, flags) = (data[0], data[1]);
+    if method & 0x0f != 8 || !(((method as u16) << 8) | flags as u16).is_multiple_of(31) {
+        return Err(invalid("Invalid zlib header"));
+    }
+    if flags & 0x20 != 0 {
+        return Err(invalid("Preset zlib dictionaries aren't supported"));
+    }
+
+    let mut output = Vec::with_capacity(size);
+    let mut bits = BitReader::new(&data[2..]);
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => {
+                // Stored, starting at the next byte
+                bits.align();
+                let length = bits.read(16)?;
+                let complement = bits.read(16)?;
+                if length != !complement & 0xffff {
+                    return Err(invalid("Invalid stored deflate block"));
+                }
+                for _ in 0..length {
+                    output.push(bits.read(8)? as u8);
+                }
+            }
+            1 => {
+                let mut lengths = [0; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5; 30])?;
+                inflate_block(&mut bits, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(invalid("Invalid deflate block type")),
+        }
+
+        if output.len() > size {
+            return Err(invalid("Too much compressed data"));
+        }
+        if last {
+            break;
+        }
+    }
+
+    if output.len() != size {
+        return Err(invalid("Too little compressed data"));
+    }
+
+    Ok(output)
+}
+
+/// Reads the bits of a byte stream starting at the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            pos: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn read(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("Truncated deflate stream"))?;
+            self.buffer |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+
+        let value = self.buffer & ((1u64 << n) - 1) as u32;
+        self.buffer >>= n;
+        self.count -= n;
+
+        Ok(value)
+    }
+
+    /// Skips to the start of the next byte
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// A canonical Huffman code, given by the number of codes of each length and the symbols in the
+/// order of their codes
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject codes with more codes of some length than there is room for
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = 2 * left - count as i32;
+            if left < 0 {
+                return Err(invalid("Oversubscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    /// Decodes a symbol one bit at a time, the codes of each length following those of the
+    /// previous length
+    fn decode(&self, bits: &mut BitReader) -> io::Result<usize> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize] as usize);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid("Invalid Huffman code"))
+    }
+}
+
+/// Reads the literal and length code and the distance code of a dynamic block
+fn read_dynamic_codes(bits: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(invalid("Too many deflate codes"));
+    }
+
+    let mut code_lengths = [0; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = bits.read(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    // Both codes' lengths are compressed together, with repeats that may cross between them
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_length_code.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                if index == 0 {
+                    return Err(invalid("Repeated code length without a previous one"));
+                }
+                (lengths[index - 1], 3 + bits.read(2)? as usize)
+            }
+            17 => (0, 3 + bits.read(3)? as usize),
+            _ => (0, 11 + bits.read(7)? as usize),
+        };
+        if index + repeat > lengths.len() {
+            return Err(invalid("Too many code lengths"));
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("Deflate code without end of block"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+/// Decodes the symbols of a compressed block up to its end
+fn inflate_block(
+    bits: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(invalid("Invalid deflate length"));
+                }
+                let length = LENGTH_BASE[index] + bits.read(LENGTH_EXTRA[index])? as usize;
+
+                let index = distances.decode(bits)?;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(invalid("Invalid deflate distance"));
+                }
+                let distance = DISTANCE_BASE[index] + bits.read(DISTANCE_EXTRA[index])? as usize;
+                if distance > output.len() {
+                    return Err(invalid("Deflate distance before the start"));
+                }
+
+                // Copy byte by byte, as the source may overlap what's being written
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_block() {
+        let data = [
+            0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o', 0, 0, 0, 0,
+        ];
+        assert_eq!(inflate_zlib(&data, 5).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn fixed_codes() {
+        let data = [
+            120, 218, 75, 76, 74, 78, 132, 33, 133, 140, 212, 156, 156, 124, 100, 18, 0, 172, 255,
+            11, 53,
+        ];
+        let expected = b"abcabcabcabc hello hello hello";
+        assert_eq!(inflate_zlib(&data, expected.len()).unwrap(), expected);
+    }
+
+    #[test]
+    fn dynamic_codes() {
+        let data = [
+            120, 218, 189, 213, 161, 1, 0, 64, 8, 195, 192, 89, 161, 45, 236, 191, 193, 139, 159,
+            129, 168, 248, 51, 169, 142, 148, 174, 203, 140, 61, 106, 157, 38, 107, 121, 115, 154,
+            91, 165, 31, 1, 88, 1, 176, 2, 96, 53, 128, 181, 0, 150, 0, 44, 1, 88, 6, 176, 4, 96,
+            13, 128, 37, 0, 43, 0, 150, 1, 44, 3, 88, 192, 6, 3, 108, 208, 15, 235, 216, 13, 9,
+        ];
+        let expected: Vec<u8> = (0..2000)
+            .map(|i| ((i * i) % 7 + (i / 100) % 3 + 97) as u8)
+            .collect();
+        assert_eq!(inflate_zlib(&data, expected.len()).unwrap(), expected);
+    }
+
+    #[test]
+    fn invalid_streams() {
+        // Not deflate, a corrupted stored length, and data ending early or running long
+        assert!(inflate_zlib(&[0x79, 0x9c, 0, 0, 0, 0], 0).is_err());
+        assert!(inflate_zlib(
+            &[0x78, 0x01, 0x01, 0x05, 0x00, 0xfb, 0xff, 1, 2, 3, 4, 5],
+            5
+        )
+        .is_err());
+        assert!(inflate_zlib(&[0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, 1, 2], 5).is_err());
+        assert!(inflate_zlib(
+            &[0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, 1, 2, 3, 4, 5],
+            4
+        )
+        .is_err());
+    }
+}
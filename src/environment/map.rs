@@ -0,0 +1,427 @@
+use std::{
+    f64::consts::PI,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use rand::Rng;
+
+use crate::{color::Color, vec3::Vec3};
+
+use super::{exr, Environment};
+
+/// The most pixels a Radiance HDR image may have, so that a corrupt resolution can't make the
+/// decoder allocate without bound
+const MAX_RGBE_PIXELS: usize = 1 << 27;
+
+/// An environment lit by a latitude-longitude (equirectangular) image, whose top row looks
+/// straight up and whose columns go around the y-axis.
+///
+/// Directions are sampled proportionally to the luminance of the pixels, so small bright
+/// features like the sun are found by shadow rays rather than by chance.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// Pixels in rows from top to bottom
+    pixels: Vec<Color>,
+    /// Rotation around the y-axis in degrees
+    rotation: f64,
+    intensity: f64,
+    /// Probability of sampling each pixel, proportional to its luminance times its solid angle
+    probabilities: Vec<f64>,
+    /// Cumulative distribution of picking each row
+    row_cdf: Vec<f64>,
+    /// Cumulative distributions of picking each column within its row
+    column_cdfs: Vec<f64>,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> EnvironmentMap {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "Image size doesn't match its pixels"
+        );
+
+        // Rows towards the poles cover less solid angle
+        let mut probabilities: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let theta = PI * ((i / width) as f64 + 0.5) / (height as f64);
+                pixel.luminance().max(0.0) * theta.sin()
+            })
+            .collect();
+        let total: f64 = probabilities.iter().sum();
+        if total > 0.0 {
+            probabilities.iter_mut().for_each(|p| *p /= total);
+        }
+
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut column_cdfs = Vec::with_capacity(width * height);
+        let mut cumulative_rows = 0.0;
+        for row in probabilities.chunks_exact(width) {
+            let row_total: f64 = row.iter().sum();
+            cumulative_rows += row_total;
+            row_cdf.push(cumulative_rows);
+
+            let mut cumulative = 0.0;
+            for (i, p) in row.iter().enumerate() {
+                // Rows that are never picked still need a valid distribution
+                if row_total > 0.0 {
+                    cumulative += p / row_total;
+                } else {
+                    cumulative = (i + 1) as f64 / (width as f64);
+                }
+                column_cdfs.push(cumulative);
+            }
+        }
+
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            intensity: 1.0,
+            probabilities,
+            row_cdf,
+            column_cdfs,
+        }
+    }
+
+    /// Loads a Radiance HDR (RGBE) or an OpenEXR image, telling them apart by their contents
+    pub fn load(path: impl AsRef<Path>) -> io::Result<EnvironmentMap> {
+        let data = fs::read(path)?;
+
+        let (width, height, pixels) = if data.starts_with(&exr::MAGIC) {
+            exr::read_exr(&data)?
+        } else {
+            read_rgbe(&data)?
+        };
+        Ok(EnvironmentMap::new(width, height, pixels))
+    }
+
+    /// Rotates the environment by `rotation` degrees around the y-axis
+    pub fn with_rotation(self, rotation: f64) -> EnvironmentMap {
+        EnvironmentMap { rotation, ..self }
+    }
+
+    /// Scales the radiance of the whole environment
+    pub fn with_intensity(self, intensity: f64) -> EnvironmentMap {
+        EnvironmentMap { intensity, ..self }
+    }
+
+    /// The index of the pixel seen along `direction`, and the sine of the direction's polar angle
+    fn pixel(&self, direction: Vec3) -> (usize, f64) {
+        let phi = direction.z().atan2(direction.x()) - self.rotation.to_radians();
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+
+        let u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = theta / PI;
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        (j * self.width + i, theta.sin())
+    }
+
+    /// Converts the probability of picking a pixel to a density with respect to solid angle
+    fn solid_angle_pdf(&self, probability: f64, sin_theta: f64) -> f64 {
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        probability * (self.width * self.height) as f64 / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let (pixel, _) = self.pixel(direction);
+        self.intensity * self.pixels[pixel]
+    }
+
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        if self.row_cdf.last().is_none_or(|&total| total <= 0.0) {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let pick = |cdf: &[f64], xi: f64| cdf.partition_point(|&c| c < xi).min(cdf.len() - 1);
+
+        let j = pick(
+            &self.row_cdf,
+            rng.gen::<f64>() * self.row_cdf[self.height - 1],
+        );
+        let columns = &self.column_cdfs[j * self.width..(j + 1) * self.width];
+        let i = pick(columns, rng.gen::<f64>() * columns[self.width - 1]);
+
+        // Pick a uniformly distributed point inside the pixel
+        let u = (i as f64 + rng.gen::<f64>()) / (self.width as f64);
+        let v = (j as f64 + rng.gen::<f64>()) / (self.height as f64);
+        let phi = 2.0 * PI * u + self.rotation.to_radians();
+        let theta = PI * v;
+
+        let direction = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        );
+        let pdf = self.solid_angle_pdf(self.probabilities[j * self.width + i], theta.sin());
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some((direction, pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let (pixel, sin_theta) = self.pixel(direction);
+        self.solid_angle_pdf(self.probabilities[pixel], sin_theta)
+    }
+}
+
+/// Decodes a Radiance HDR image with either flat or run-length encoded scanlines
+fn read_rgbe(data: &[u8]) -> io::Result<(usize, usize, Vec<Color>)> {
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+    let mut pos = 0;
+    let mut read_line = || {
+        let length = data[pos..].iter().position(|&b| b == b'\n')?;
+        let line = String::from_utf8_lossy(&data[pos..pos + length]).into_owned();
+        pos += length + 1;
+        Some(line)
+    };
+
+    // The header is a list of variables ending with an empty line
+    let magic = read_line().ok_or_else(|| invalid("Truncated HDR header"))?;
+    if !magic.starts_with("#?") {
+        return Err(invalid("Not a Radiance HDR image"));
+    }
+    loop {
+        let line = read_line().ok_or_else(|| invalid("Truncated HDR header"))?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid("Only RGBE HDR images are supported"));
+        }
+    }
+
+    // Only the standard orientation of rows from top to bottom is supported
+    let resolution = read_line().ok_or_else(|| invalid("Missing HDR resolution"))?;
+    let tokens: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match tokens[..] {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        _ => return Err(invalid("Unsupported HDR orientation")),
+    };
+    let height = height.map_err(|_| invalid("Invalid HDR resolution"))?;
+    let width = width.map_err(|_| invalid("Invalid HDR resolution"))?;
+    if width == 0 || height == 0 {
+        return Err(invalid("Empty HDR image"));
+    }
+    let pixel_count = width
+        .checked_mul(height)
+        .filter(|&count| count <= MAX_RGBE_PIXELS)
+        .ok_or_else(|| invalid("HDR image too large"))?;
+
+    let mut byte = || {
+        let value = data.get(pos).copied();
+        pos += 1;
+        value.ok_or_else(|| invalid("Truncated HDR pixel data"))
+    };
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let header = [byte()?, byte()?, byte()?, byte()?];
+        let encoded_width = ((header[2] as usize) << 8) | header[3] as usize;
+
+        if (8..0x8000).contains(&width) && header[..2] == [2, 2] && encoded_width == width {
+            // Run-length encoded, one channel after the other
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = byte()? as usize;
+                    if count > 128 {
+                        let value = byte()?;
+                        let run = count - 128;
+                        if x + run > width {
+                            return Err(invalid("Invalid HDR run length"));
+                        }
+                        scanline[x..x + run]
+                            .iter_mut()
+                            .for_each(|p| p[channel] = value);
+                        x += run;
+                    } else {
+                        if count == 0 || x + count > width {
+                            return Err(invalid("Invalid HDR run length"));
+                        }
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = byte()?;
+                        }
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            // Flat, where the four bytes already read are the first pixel
+            scanline[0] = header;
+            for pixel in scanline.iter_mut().skip(1) {
+                *pixel = [byte()?, byte()?, byte()?, byte()?];
+            }
+        }
+
+        pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                return Color::new(0.0, 0.0, 0.0);
+            }
+
+            // The shared exponent is biased by 128, and the mantissas are 8 bit fractions
+            let scale = 2.0_f64.powi(e as i32 - 136);
+            Color::new(
+                (r as f64 + 0.5) * scale,
+                (g as f64 + 0.5) * scale,
+                (b as f64 + 0.5) * scale,
+            )
+        }));
+    }
+
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(resolution: &str) -> Vec<u8> {
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution).into_bytes()
+    }
+
+    fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+        let scale = 2.0_f64.powi(e as i32 - 136);
+        Color::new(
+            (r as f64 + 0.5) * scale,
+            (g as f64 + 0.5) * scale,
+            (b as f64 + 0.5) * scale,
+        )
+    }
+
+    fn assert_color(actual: Color, expected: Color) {
+        for channel in 0..3 {
+            assert!((actual[channel] - expected[channel]).abs() < 1.0e-12);
+        }
+    }
+
+    #[test]
+    fn flat_scanlines_from_top_to_bottom() {
+        let top = [[128, 64, 32, 129], [0, 0, 0, 0]];
+        let bottom = [[10, 20, 30, 140], [255, 255, 255, 128]];
+
+        let mut data = header("-Y 2 +X 2");
+        data.extend(top.iter().chain(&bottom).flatten());
+        let (width, height, pixels) = read_rgbe(&data).unwrap();
+
+        assert_eq!((width, height), (2, 2));
+        assert_color(pixels[0], rgbe_to_color(top[0]));
+        assert_color(pixels[1], Color::new(0.0, 0.0, 0.0));
+        assert_color(pixels[2], rgbe_to_color(bottom[0]));
+        assert_color(pixels[3], rgbe_to_color(bottom[1]));
+    }
+
+    #[test]
+    fn run_length_encoded_scanlines() {
+        let mut data = header("-Y 2 +X 8");
+        for row in 0..2u8 {
+            data.extend([2, 2, 0, 8]);
+            // Red as a single run, green as literals, blue mixed and the exponent as a run
+            data.extend([128 + 8, 100 + row]);
+            data.extend([8, 1, 2, 3, 4, 5, 6, 7, 8]);
+            data.extend([128 + 3, 50, 5, 60, 61, 62, 63, 64]);
+            data.extend([128 + 8, 130]);
+        }
+        let (width, height, pixels) = read_rgbe(&data).unwrap();
+
+        assert_eq!((width, height), (8, 2));
+        for row in 0..2u8 {
+            for x in 0..8u8 {
+                let blue = if x < 3 { 50 } else { 57 + x };
+                let expected = rgbe_to_color([100 + row, x + 1, blue, 130]);
+                assert_color(pixels[(row as usize) * 8 + x as usize], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_images() {
+        let mut flipped = header("+Y 1 +X 1");
+        flipped.extend([1, 1, 1, 128]);
+        assert!(read_rgbe(&flipped).is_err());
+
+        let mut truncated = header("-Y 1 +X 2");
+        truncated.extend([1, 1, 1, 128, 1, 1]);
+        assert!(read_rgbe(&truncated).is_err());
+
+        let mut overlong_run = header("-Y 1 +X 8");
+        overlong_run.extend([2, 2, 0, 8, 128 + 9, 1]);
+        assert!(read_rgbe(&overlong_run).is_err());
+
+        assert!(read_rgbe(b"P6 1 1 255\n").is_err());
+
+        // Resolutions whose pixel count overflows or exceeds the limit aren't allocated
+        let huge = format!("-Y {} +X {}", usize::MAX / 2, 4);
+        assert!(read_rgbe(&header(&huge)).is_err());
+        assert!(read_rgbe(&header("-Y 100000 +X 100000")).is_err());
+    }
+
+    /// A small map with a bright spot, a dark row and some variation elsewhere
+    fn test_map() -> EnvironmentMap {
+        let (width, height) = (8, 4);
+        let pixels = (0..width * height)
+            .map(|i| match i {
+                10 => Color::new(50.0, 40.0, 30.0),
+                24..=31 => Color::new(0.0, 0.0, 0.0),
+                _ => Color::new(0.1, 0.2, 0.3) * (1.0 + (i % 5) as f64),
+            })
+            .collect();
+
+        EnvironmentMap::new(width, height, pixels).with_rotation(30.0)
+    }
+
+    #[test]
+    fn sampled_pdf_matches_pdf() {
+        let map = test_map();
+        for _ in 0..1000 {
+            let (direction, pdf) = map.sample().unwrap();
+            assert!((direction.length() - 1.0).abs() < 1.0e-9);
+            assert!((map.pdf(direction) - pdf).abs() < 1.0e-6 * pdf);
+            assert!(map.radiance(direction).luminance() > 0.0);
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let map = test_map();
+
+        // Midpoint rule over the sphere in the map's own coordinates
+        let (steps_theta, steps_phi) = (400, 800);
+        let mut integral = 0.0;
+        for j in 0..steps_theta {
+            let theta = PI * (j as f64 + 0.5) / (steps_theta as f64);
+            for i in 0..steps_phi {
+                let phi = 2.0 * PI * (i as f64 + 0.5) / (steps_phi as f64);
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                let solid_angle =
+                    theta.sin() * (PI / steps_theta as f64) * (2.0 * PI) / (steps_phi as f64);
+
+                integral += map.pdf(direction) * solid_angle;
+            }
+        }
+
+        assert!((integral - 1.0).abs() < 1.0e-2, "integral = {}", integral);
+    }
+}
@@ -0,0 +1,30 @@
+mod exr;
+mod gradient;
+mod inflate;
+mod map;
+mod sky;
+
+pub use gradient::Gradient;
+pub use map::EnvironmentMap;
+pub use sky::Sky;
+
+use crate::{color::Color, vec3::Vec3};
+
+/// Light arriving from infinitely far away, seen by rays that leave the world
+pub trait Environment: Send + Sync {
+    /// The radiance seen when looking along the unit vector `direction`
+    fn radiance(&self, direction: Vec3) -> Color;
+
+    /// Samples a unit vector towards the environment, together with its probability density with
+    /// respect to solid angle.
+    ///
+    /// Environments that can't be sampled are only found by scattered rays.
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /// The probability density of `sample` returning `direction`
+    fn pdf(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+}
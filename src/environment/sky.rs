@@ -0,0 +1,274 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{color::Color, onb::Onb, vec3::Vec3};
+
+use super::Environment;
+
+/// Converts the model's luminance in kcd/m² to the radiance of the renderer, so that the sky is
+/// about as bright as the default gradient
+const SCALE: f64 = 0.05;
+
+/// Luminance of the sun outside of the atmosphere in kcd/m²
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+/// Angular radius of the sun disk as seen from the earth
+const SUN_ANGULAR_RADIUS: f64 = 0.267;
+
+/// Wavelengths (in micrometers) representing the red, green and blue channel
+const RGB_WAVELENGTHS: [f64; 3] = [0.65, 0.532, 0.45];
+
+/// How often the sun disk rather than the sky is sampled, while the sun is up
+const SUN_PROBABILITY: f64 = 0.5;
+
+/// The analytic clear sky model of Preetham, Shirley and Smits (1999), with a sun disk dimmed
+/// and reddened by the atmosphere.
+///
+/// Directions below the horizon see the sky at the horizon, ground is expected to be modeled
+/// as part of the world.
+pub struct Sky {
+    /// Unit vector towards the sun
+    sun_direction: Vec3,
+    /// Zenith angle of the sun in radians
+    theta_sun: f64,
+    /// The sky's luminance and chromaticity at the zenith
+    zenith: [f64; 3],
+    /// Perez coefficients A to E of the luminance and chromaticity distributions
+    perez: [[f64; 5]; 3],
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+    intensity: f64,
+}
+
+impl Sky {
+    /// `elevation` is the angle of the sun above the horizon and `azimuth` its angle around the
+    /// y-axis starting at the x-axis, both in degrees, while `turbidity` describes the haziness of
+    /// the atmosphere from about 2 for a very clear to 10 for a hazy sky
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Sky {
+        // The model isn't defined for the sun below the horizon
+        let elevation = elevation.clamp(0.0, 90.0).to_radians();
+        let azimuth = azimuth.to_radians();
+        let t = turbidity;
+
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        );
+        let theta_sun = 0.5 * PI - elevation;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let thetas = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+        let polynomial = |t2: [f64; 4], t1: [f64; 4], t0: [f64; 4]| {
+            (0..4)
+                .map(|i| (t.powi(2) * t2[i] + t * t1[i] + t0[i]) * thetas[i])
+                .sum::<f64>()
+        };
+        let x = polynomial(
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        );
+        let y = polynomial(
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        );
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // Rayleigh and aerosol extinction of sunlight along its path through the atmosphere,
+        // whose relative length is given by the optical mass
+        let optical_mass =
+            1.0 / (theta_sun.cos() + 0.15 * (93.885 - theta_sun.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * t - 0.04586;
+        let mut sun_radiance = Color::new(0.0, 0.0, 0.0);
+        for (channel, wavelength) in RGB_WAVELENGTHS.into_iter().enumerate() {
+            let rayleigh = -0.008735 * wavelength.powf(-4.08);
+            let aerosol = -beta * wavelength.powf(-1.3);
+            sun_radiance[channel] = SUN_LUMINANCE * ((rayleigh + aerosol) * optical_mass).exp();
+        }
+
+        Sky {
+            sun_direction,
+            theta_sun,
+            zenith: [luminance, x, y],
+            perez,
+            sun_radiance,
+            cos_sun_radius: SUN_ANGULAR_RADIUS.to_radians().cos(),
+            intensity: 1.0,
+        }
+    }
+
+    /// Scales the radiance of both the sky and the sun
+    pub fn with_intensity(self, intensity: f64) -> Sky {
+        Sky { intensity, ..self }
+    }
+
+    /// The Perez distribution for a view direction at zenith angle `theta` and angle `gamma`
+    /// away from the sun
+    fn perez(coefficients: [f64; 5], theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = coefficients;
+        (1.0 + a * (b / theta.cos()).exp())
+            * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+
+    /// The radiance of the sky alone, without the sun disk
+    fn sky_radiance(&self, direction: Vec3) -> Color {
+        // Look at the horizon instead of below it
+        let theta = direction.y().clamp(1.0e-3, 1.0).acos();
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * Sky::perez(self.perez[i], theta, gamma)
+                / Sky::perez(self.perez[i], 0.0, self.theta_sun)
+        });
+
+        // From xyY to CIE XYZ to linear sRGB
+        let big_x = x * luminance / y;
+        let big_z = (1.0 - x - y) * luminance / y;
+        let rgb = Color::new(
+            3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+            -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+            0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+        );
+
+        Color::new(rgb[0].max(0.0), rgb[1].max(0.0), rgb[2].max(0.0))
+    }
+
+    fn sun_probability(&self) -> f64 {
+        if self.sun_direction.y() > 0.0 {
+            SUN_PROBABILITY
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let mut radiance = self.sky_radiance(direction);
+        if direction.dot(self.sun_direction) >= self.cos_sun_radius {
+            radiance += self.sun_radiance;
+        }
+
+        (SCALE * self.intensity) * radiance
+    }
+
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        let mut rng = rand::thread_rng();
+
+        let direction = if rng.gen::<f64>() < self.sun_probability() {
+            // Uniformly within the cone of the sun disk
+            let cos_theta = 1.0 - rng.gen::<f64>() * (1.0 - self.cos_sun_radius);
+            let sin_theta = (1.0 - cos_theta.powi(2)).max(0.0).sqrt();
+            let phi = 2.0 * PI * rng.gen::<f64>();
+
+            Onb::from_w(self.sun_direction).to_world(Vec3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ))
+        } else {
+            // The sky is brightest towards the zenith, so sample the cosine to the y-axis
+            let local = Vec3::random_cosine_direction();
+            Vec3::new(local.x(), local.z(), local.y())
+        };
+
+        let pdf = self.pdf(direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some((direction, pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let sun_probability = self.sun_probability();
+
+        let sun = if direction.dot(self.sun_direction) >= self.cos_sun_radius {
+            1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+        } else {
+            0.0
+        };
+        let sky = direction.y().max(0.0) / PI;
+
+        sun_probability * sun + (1.0 - sun_probability) * sky
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Integrates the density over the sphere around the sun, in separate bands for the sun disk
+    /// and the rest so that the disk's edge falls between them
+    fn integrate_pdf(sky: &Sky) -> f64 {
+        let frame = Onb::from_w(sky.sun_direction);
+        let (bands, steps) = (500, 500);
+
+        let mut integral = 0.0;
+        for (low, high) in [(-1.0, sky.cos_sun_radius), (sky.cos_sun_radius, 1.0)] {
+            let d_cos = (high - low) / bands as f64;
+            let d_phi = 2.0 * PI / steps as f64;
+            for i in 0..bands {
+                let cos_theta = low + (i as f64 + 0.5) * d_cos;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                for j in 0..steps {
+                    let phi = (j as f64 + 0.5) * d_phi;
+                    let direction = frame.to_world(Vec3::new(
+                        sin_theta * phi.cos(),
+                        sin_theta * phi.sin(),
+                        cos_theta,
+                    ));
+                    integral += sky.pdf(direction) * d_cos * d_phi;
+                }
+            }
+        }
+
+        integral
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        for elevation in [0.0, 30.0, 90.0] {
+            let integral = integrate_pdf(&Sky::new(elevation, 45.0, 3.0));
+            assert!((integral - 1.0).abs() < 1.0e-3, "{}", integral);
+        }
+    }
+
+    #[test]
+    fn samples_match_pdf() {
+        let sky = Sky::new(30.0, 45.0, 3.0);
+        for _ in 0..1000 {
+            let (direction, pdf) = sky.sample().unwrap();
+            assert!((direction.length() - 1.0).abs() < 1.0e-9);
+            assert_eq!(pdf, sky.pdf(direction));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::color::Color;
+use crate::density_grid::DensityGrid;
+use crate::hit::{Hit, HitRecord};
+use crate::material::Scatter;
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A participating medium like smoke or clouds, whose density varies according to a voxel grid
+/// stretched over an axis-aligned box.
+///
+/// Collisions inside the medium are found by delta tracking (Woodcock tracking) against the
+/// maximum density of the grid.
+pub struct HeterogeneousMedium {
+    grid: DensityGrid,
+    min: Point3,
+    max: Point3,
+    /// Converts the grid's densities to extinction per unit of length
+    density_scale: f64,
+    phase: Arc<dyn Scatter>,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        grid: DensityGrid,
+        min: Point3,
+        max: Point3,
+        density_scale: f64,
+        phase: Arc<dyn Scatter>,
+    ) -> HeterogeneousMedium {
+        HeterogeneousMedium {
+            grid,
+            min,
+            max,
+            density_scale,
+            phase,
+        }
+    }
+
+    /// The range of the ray inside the medium's box, using the slab method
+    fn bounds(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_enter = t_min;
+        let mut t_exit = t_max;
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction()[axis];
+            let mut t0 = (self.min[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_enter = t_enter.max(t0);
+            t_exit = t_exit.min(t1);
+            if t_exit <= t_enter {
+                return None;
+            }
+        }
+
+        Some((t_enter, t_exit))
+    }
+
+    fn extinction(&self, p: Point3) -> f64 {
+        let local = Point3::new(
+            (p.x() - self.min.x()) / (self.max.x() - self.min.x()),
+            (p.y() - self.min.y()) / (self.max.y() - self.min.y()),
+            (p.z() - self.min.z()) / (self.max.z() - self.min.z()),
+        );
+
+        self.density_scale * self.grid.density(local)
+    }
+}
+
+impl Hit for HeterogeneousMedium {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = self.bounds(r, t_min, t_max)?;
+
+        let majorant = self.density_scale * self.grid.max_density();
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let ray_length = r.direction().length();
+        let mut t = t_enter;
+
+        loop {
+            // Step to the next tentative collision against the majorant
+            t -= (1.0 - rng.gen::<f64>()).ln() / (majorant * ray_length);
+            if t >= t_exit {
+                return None;
+            }
+
+            // Accept it as a real collision with the probability of the local density
+            let point = r.at(t);
+            if rng.gen::<f64>() * majorant < self.extinction(point) {
+                // The surface orientation of the record is meaningless inside the volume
+                let normal = (-1.0 / ray_length) * r.direction();
+                let tangent = if normal.x().abs() > 0.9 {
+                    Vec3::new(0.0, 1.0, 0.0)
+                } else {
+                    Vec3::new(1.0, 0.0, 0.0)
+                };
+
+                return Some(HitRecord::new(
+                    point,
+                    self.phase.clone(),
+                    t,
+                    (0.0, 0.0),
+                    r,
+                    normal,
+                    (tangent, normal.cross(tangent)),
+                ));
+            }
+        }
+    }
+
+    /// Ratio tracking: the same tentative collisions as in `hit`, but instead of stopping at the
+    /// first real one, the transmittance is multiplied by the probability of passing each of them
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        let (t_enter, t_exit) = match self.bounds(r, t_min, t_max) {
+            Some(bounds) => bounds,
+            None => return Color::new(1.0, 1.0, 1.0),
+        };
+
+        let majorant = self.density_scale * self.grid.max_density();
+        if majorant <= 0.0 {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        let mut rng = rand::thread_rng();
+        let ray_length = r.direction().length();
+        let mut t = t_enter;
+        let mut transmittance = 1.0;
+
+        loop {
+            t -= (1.0 - rng.gen::<f64>()).ln() / (majorant * ray_length);
+            if t >= t_exit {
+                return Color::new(transmittance, transmittance, transmittance);
+            }
+
+            transmittance *= 1.0 - self.extinction(r.at(t)) / majorant;
+        }
+    }
+}
@@ -1,19 +1,33 @@
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
+use crate::color::Color;
+use crate::environment::{Environment, Gradient};
+use crate::light::{Light, LightTree};
 use crate::material::Scatter;
 use crate::point3::Point3;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
+#[derive(Clone)]
 pub struct HitRecord {
     /// The point of intersection
     pub point: Point3,
     /// The normal of the surface at the point of intersection
     pub normal: Vec3,
+    /// The direction of increasing `u` along the surface, perpendicular to the normal
+    pub tangent: Vec3,
+    /// Completes the tangent and normal to a right-handed frame
+    pub bitangent: Vec3,
+    /// The partial derivatives of the point with respect to the surface coordinates
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     /// The material of the object that was hit
     pub material: Arc<dyn Scatter>,
     /// The distance from the ray's origin to the hit point
     pub t: f64,
+    /// The surface coordinates of the hit point
+    pub u: f64,
+    pub v: f64,
     /// Whether or not the ray was inside the object
     pub front_face: bool,
 }
@@ -23,19 +37,46 @@ impl HitRecord {
         point: Point3,
         material: Arc<dyn Scatter>,
         t: f64,
+        (u, v): (f64, f64),
         ray: &Ray,
         outward_normal: Vec3,
+        (dpdu, dpdv): (Vec3, Vec3),
     ) -> HitRecord {
         let (front_face, normal) = Self::determine_face_normal(ray, outward_normal);
+
+        // Gram-Schmidt the direction of increasing u so that the shading frame stays orthonormal
+        let tangent = (dpdu - dpdu.dot(normal) * normal).normalized();
+        let bitangent = normal.cross(tangent);
+
         HitRecord {
             point,
             material,
             normal,
+            tangent,
+            bitangent,
+            dpdu,
+            dpdv,
             t,
+            u,
+            v,
             front_face,
         }
     }
 
+    /// A copy of this record with a perturbed shading normal, keeping the tangent frame orthonormal
+    pub fn with_normal(&self, normal: Vec3) -> HitRecord {
+        let normal = normal.normalized();
+        let tangent = (self.tangent - self.tangent.dot(normal) * normal).normalized();
+        let bitangent = normal.cross(tangent);
+
+        HitRecord {
+            normal,
+            tangent,
+            bitangent,
+            ..self.clone()
+        }
+    }
+
     pub fn determine_face_normal(r: &Ray, outward_normal: Vec3) -> (bool, Vec3) {
         let front_face = r.direction().dot(outward_normal) < 0.0;
         let normal = if front_face {
@@ -44,38 +85,149 @@ impl HitRecord {
             (-1.0) * outward_normal
         };
 
-        return (front_face, normal);
+        (front_face, normal)
     }
 }
 
 pub trait Hit: Send + Sync {
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// The fraction of light that makes it through this object along the ray, used for shadows
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        if self.hit(r, t_min, t_max).is_some() {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    /// The total power emitted by the object, zero if it doesn't emit light
+    fn power(&self) -> f64 {
+        0.0
+    }
+
+    /// The minimum and maximum corner of a box around the object, only needed for emitters
+    fn bounding_box(&self) -> Option<(Point3, Point3)> {
+        None
+    }
+
+    /// The axis and the cosine of the spread of a cone containing all the object's outward
+    /// normals, by default all directions
+    fn normal_cone(&self) -> (Vec3, f64) {
+        (Vec3::new(0.0, 0.0, 1.0), -1.0)
+    }
+
+    /// Samples a point on the object as seen from `origin`, given as the record of a ray from
+    /// `origin` hitting it there, together with the probability density of the ray's direction
+    /// with respect to solid angle
+    fn sample_point(&self, _origin: Point3) -> Option<(HitRecord, f64)> {
+        None
+    }
+
+    /// The probability density of `sample_point` choosing the point of `rec`
+    fn point_pdf(&self, _origin: Point3, _rec: &HitRecord) -> f64 {
+        0.0
+    }
 }
 
-pub struct World(Vec<Box<dyn Hit>>);
+pub struct World {
+    objects: Vec<Box<dyn Hit>>,
+    lights: Vec<Box<dyn Light>>,
+    environment: Box<dyn Environment>,
+    /// Built over the emissive objects the first time they're sampled
+    emitters: OnceLock<LightTree>,
+}
 
 impl World {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self {
+            objects: Vec::new(),
+            lights: Vec::new(),
+            environment: Box::new(Gradient::new(
+                Color::new(1.0, 1.0, 1.0),
+                Color::new(0.5, 0.7, 1.0),
+            )),
+            emitters: OnceLock::new(),
+        }
     }
 
     pub fn push(&mut self, element: Box<dyn Hit>) {
-        self.0.push(element);
+        self.objects.push(element);
     }
-}
 
-impl Hit for World {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    pub fn push_light(&mut self, light: Box<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    /// Replaces the default blue sky gradient
+    pub fn set_environment(&mut self, environment: Box<dyn Environment>) {
+        self.environment = environment;
+    }
+
+    pub fn environment(&self) -> &dyn Environment {
+        self.environment.as_ref()
+    }
+
+    /// Like `hit`, but also returns the index of the object that was hit
+    pub fn hit_object(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(usize, HitRecord)> {
         let mut hit_record = None;
         let mut t_closest = t_max;
 
-        for object in &self.0 {
+        for (index, object) in self.objects.iter().enumerate() {
             if let Some(rec) = object.hit(r, t_min, t_closest) {
                 t_closest = rec.t;
-                hit_record = Some(rec);
+                hit_record = Some((index, rec));
             }
         }
 
         hit_record
     }
+
+    fn emitters(&self) -> &LightTree {
+        self.emitters.get_or_init(|| LightTree::new(&self.objects))
+    }
+
+    /// Picks an emissive object by its estimated contribution at `point` and samples a point on
+    /// it, returning its record and the probability density of its direction with respect to
+    /// solid angle
+    pub fn sample_emitter(&self, point: Point3) -> Option<(HitRecord, f64)> {
+        let (index, probability) = self.emitters().sample(point)?;
+        let (rec, pdf) = self.objects[index].sample_point(point)?;
+
+        Some((rec, probability * pdf))
+    }
+
+    /// The probability density of `sample_emitter` for `point` returning `rec` on the object with
+    /// the given index
+    pub fn emitter_pdf(&self, point: Point3, index: usize, rec: &HitRecord) -> f64 {
+        let probability = self.emitters().probability(point, index);
+        if probability <= 0.0 {
+            return 0.0;
+        }
+
+        probability * self.objects[index].point_pdf(point, rec)
+    }
+}
+
+impl Hit for World {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.hit_object(r, t_min, t_max).map(|(_, rec)| rec)
+    }
+
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        let mut transmittance = Color::new(1.0, 1.0, 1.0);
+
+        for object in &self.objects {
+            transmittance = transmittance * object.transmittance(r, t_min, t_max);
+            if transmittance.is_black() {
+                break;
+            }
+        }
+
+        transmittance
+    }
 }
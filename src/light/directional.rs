@@ -0,0 +1,31 @@
+use crate::{color::Color, point3::Point3, vec3::Vec3};
+
+use super::{Light, LightSample};
+
+/// A light infinitely far away, like the sun, illuminating everything from the same direction
+pub struct DirectionalLight {
+    /// Unit vector pointing towards the light
+    to_light: Vec3,
+    /// The power arriving per unit area perpendicular to the light
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    /// `direction` is the direction the light travels in
+    pub fn new(direction: Vec3, irradiance: Color) -> DirectionalLight {
+        DirectionalLight {
+            to_light: (-1.0) * direction.normalized(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.to_light,
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+        })
+    }
+}
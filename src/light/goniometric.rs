@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::{color::Color, onb::Onb, point3::Point3};
+
+use super::{IesProfile, Light, LightSample};
+
+/// A point light whose intensity varies with direction following a measured profile, such as a
+/// spot or a downlight described by an IES file
+pub struct GoniometricLight {
+    position: Point3,
+    /// Local frame with the profile's axis (zero vertical angle) as w, and zero horizontal angle
+    /// towards u
+    frame: Onb,
+    /// The intensity in the direction the profile is brightest
+    intensity: Color,
+    profile: Arc<IesProfile>,
+    /// Rotation of the profile around its axis in degrees
+    rotation: f64,
+}
+
+impl GoniometricLight {
+    /// Points the axis of `profile` from `position` towards `look_at`, scaled so that its
+    /// brightest direction has the given intensity
+    pub fn new(
+        position: Point3,
+        look_at: Point3,
+        intensity: Color,
+        profile: Arc<IesProfile>,
+    ) -> GoniometricLight {
+        GoniometricLight {
+            position,
+            frame: Onb::from_w(look_at - position),
+            intensity,
+            profile,
+            rotation: 0.0,
+        }
+    }
+
+    /// Rotates the profile by `rotation` degrees around its axis
+    pub fn with_rotation(self, rotation: f64) -> GoniometricLight {
+        GoniometricLight { rotation, ..self }
+    }
+}
+
+impl Light for GoniometricLight {
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+        if distance <= 0.0 {
+            // The falloff is unbounded at the light itself
+            return None;
+        }
+        let direction = to_light / distance;
+
+        // The direction of the emitted light in the profile's frame
+        let local = self.frame.to_local((-1.0) * direction);
+        let vertical = local.z().clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = local.y().atan2(local.x()).to_degrees() - self.rotation;
+
+        let max_candela = self.profile.max_candela();
+        if max_candela <= 0.0 {
+            return None;
+        }
+        let scale = self.profile.candela(vertical, horizontal) / max_candela;
+        if scale <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: (scale / distance.powi(2)) * self.intensity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A light 4 above the origin, pointing down, that fades from its axis to nothing straight up
+    fn downlight() -> GoniometricLight {
+        let profile = IesProfile::new(vec![0.0, 90.0, 180.0], vec![0.0], vec![100.0, 50.0, 0.0]);
+
+        GoniometricLight::new(
+            Point3::new(0.0, 4.0, 0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Color::new(16.0, 32.0, 64.0),
+            Arc::new(profile),
+        )
+    }
+
+    #[test]
+    fn follows_the_profile() {
+        let light = downlight();
+
+        let sample = light.sample(Point3::new(0.0, 0.0, 0.0)).unwrap();
+        assert!((sample.direction.y() - 1.0).abs() < 1.0e-12);
+        assert!((sample.radiance[0] - 1.0).abs() < 1.0e-12);
+        assert!((sample.radiance[2] - 4.0).abs() < 1.0e-12);
+
+        // 45 degrees off the axis, halfway to the intensity at 90 degrees
+        let sample = light.sample(Point3::new(4.0, 0.0, 0.0)).unwrap();
+        assert!((sample.distance.powi(2) - 32.0).abs() < 1.0e-9);
+        assert!((sample.radiance[0] - 0.75 * 16.0 / 32.0).abs() < 1.0e-9);
+
+        assert!(light.sample(Point3::new(0.0, 8.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn nothing_at_the_light() {
+        assert!(downlight().sample(Point3::new(0.0, 4.0, 0.0)).is_none());
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+/// The luminous intensity distribution of a luminaire, as measured by a goniophotometer.
+///
+/// Intensities are given on a grid of vertical angles, measured from the luminaire's axis
+/// (nadir), and horizontal angles around it, following type C photometry.
+pub struct IesProfile {
+    /// Ascending vertical angles in degrees
+    vertical_angles: Vec<f64>,
+    /// Ascending horizontal angles in degrees
+    horizontal_angles: Vec<f64>,
+    /// Intensities in candela, all vertical angles for each horizontal angle in turn
+    candela: Vec<f64>,
+    max_candela: f64,
+}
+
+impl IesProfile {
+    pub fn new(
+        vertical_angles: Vec<f64>,
+        horizontal_angles: Vec<f64>,
+        candela: Vec<f64>,
+    ) -> IesProfile {
+        assert!(
+            !vertical_angles.is_empty() && !horizontal_angles.is_empty(),
+            "Profile without angles"
+        );
+        assert_eq!(
+            candela.len(),
+            vertical_angles.len() * horizontal_angles.len(),
+            "Intensities don't match the angles"
+        );
+
+        let max_candela = candela.iter().copied().fold(0.0, f64::max);
+
+        IesProfile {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+        }
+    }
+
+    /// Loads an IESNA LM-63 photometric data file
+    pub fn load(path: impl AsRef<Path>) -> io::Result<IesProfile> {
+        let data = fs::read(path)?;
+        IesProfile::parse(&String::from_utf8_lossy(&data))
+    }
+
+    /// Parses the contents of an IESNA LM-63 file (any version from 1986 to 2002).
+    ///
+    /// Tilt data is read but not applied, as it only matters for lamps mounted at an angle other
+    /// than the one they were measured at.
+    pub fn parse(text: &str) -> io::Result<IesProfile> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        // Keywords come first, up to the line describing the tilt
+        let mut lines = text.lines();
+        let tilt = loop {
+            let line = lines.next().ok_or_else(|| invalid("Missing TILT line"))?;
+            if let Some(tilt) = line.trim().strip_prefix("TILT=") {
+                break tilt.trim().to_string();
+            }
+        };
+
+        // Everything after it is numbers, separated by whitespace or commas
+        let mut numbers = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .parse::<f64>()
+                    .map_err(|_| invalid(&format!("Invalid number {:?}", token)))
+            });
+        let mut next = || -> io::Result<f64> {
+            numbers
+                .next()
+                .unwrap_or_else(|| Err(invalid("Truncated photometric data")))
+        };
+
+        if tilt == "INCLUDE" {
+            // Lamp to luminaire geometry, then pairs of angles and multiplying factors
+            next()?;
+            let count = next()? as usize;
+            for _ in 0..2 * count {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()? as i64;
+        let _units = next()?;
+        let _dimensions = [next()?, next()?, next()?];
+        let ballast_factor = next()?;
+        let ballast_lamp_factor = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1 {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "Only type C photometry is supported",
+            ));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid("Profile without angles"));
+        }
+
+        let vertical_angles = (0..vertical_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f64>>>()?;
+        let horizontal_angles = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<f64>>>()?;
+        if !vertical_angles.is_sorted() || !horizontal_angles.is_sorted() {
+            return Err(invalid("Angles aren't in ascending order"));
+        }
+
+        let scale = multiplier * ballast_factor * ballast_lamp_factor;
+        let candela = (0..vertical_count * horizontal_count)
+            .map(|_| Ok(scale * next()?))
+            .collect::<io::Result<Vec<f64>>>()?;
+
+        Ok(IesProfile::new(vertical_angles, horizontal_angles, candela))
+    }
+
+    /// The largest intensity in any direction
+    pub fn max_candela(&self) -> f64 {
+        self.max_candela
+    }
+
+    /// The intensity in candela at `vertical` degrees from the axis and `horizontal` degrees
+    /// around it, interpolated bilinearly between the measurements
+    pub fn candela(&self, vertical: f64, horizontal: f64) -> f64 {
+        let first = self.vertical_angles[0];
+        let last = *self.vertical_angles.last().unwrap();
+        if vertical < first || vertical > last {
+            // Luminaires emitting only downwards or upwards are measured over half the sphere
+            return 0.0;
+        }
+
+        let (v0, v1, tv) = Self::interval(&self.vertical_angles, vertical);
+        let (h0, h1, th) = self.horizontal_interval(horizontal);
+
+        let count = self.vertical_angles.len();
+        let value = |h: usize, v: usize| self.candela[h * count + v];
+        let low = (1.0 - tv) * value(h0, v0) + tv * value(h0, v1);
+        let high = (1.0 - tv) * value(h1, v0) + tv * value(h1, v1);
+
+        (1.0 - th) * low + th * high
+    }
+
+    /// The indices of the horizontal angles around `horizontal` and how far it is between them.
+    ///
+    /// Angles outside of the measured range are mirrored into it using the symmetry implied by the
+    /// first and last horizontal angle, or wrap around between the last and the first one if
+    /// the measurements go all the way around without repeating 0 as 360 degrees.
+    fn horizontal_interval(&self, horizontal: f64) -> (usize, usize, f64) {
+        let angles = &self.horizontal_angles;
+        let first = angles[0];
+        let last = *angles.last().unwrap();
+        let mut angle = horizontal.rem_euclid(360.0);
+
+        match (first, last) {
+            // Rotationally symmetric
+            _ if angles.len() == 1 => return (0, 0, 0.0),
+            // Symmetric in each quadrant
+            (0.0, 90.0) => {
+                if angle > 180.0 {
+                    angle = 360.0 - angle;
+                }
+                if angle > 90.0 {
+                    angle = 180.0 - angle;
+                }
+            }
+            // Bilaterally symmetric about the 0 to 180 degree plane
+            (0.0, 180.0) => {
+                if angle > 180.0 {
+                    angle = 360.0 - angle;
+                }
+            }
+            // Bilaterally symmetric about the 90 to 270 degree plane
+            (90.0, 270.0) => {
+                if !(90.0..=270.0).contains(&angle) {
+                    angle = (180.0 - angle).rem_euclid(360.0);
+                }
+            }
+            _ => {
+                if angle < first {
+                    angle += 360.0;
+                }
+                if angle > last {
+                    let gap = first + 360.0 - last;
+                    let t = if gap > 0.0 { (angle - last) / gap } else { 0.0 };
+                    return (angles.len() - 1, 0, t);
+                }
+            }
+        }
+
+        Self::interval(angles, angle)
+    }
+
+    /// The indices of the angles around `angle` and how far it is between them, clamped to the
+    /// ends of `angles`
+    fn interval(angles: &[f64], angle: f64) -> (usize, usize, f64) {
+        let upper = angles.partition_point(|&a| a < angle);
+        if upper == 0 {
+            return (0, 0, 0.0);
+        }
+        if upper == angles.len() {
+            return (upper - 1, upper - 1, 0.0);
+        }
+
+        let (a0, a1) = (angles[upper - 1], angles[upper]);
+        let t = if a1 > a0 {
+            (angle - a0) / (a1 - a0)
+        } else {
+            0.0
+        };
+
+        (upper - 1, upper, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file with the given horizontal angles, two vertical angles and the intensities of each
+    /// horizontal angle in turn
+    fn file(tilt: &str, multiplier: f64, horizontal: &[f64], candela: &[f64]) -> String {
+        let join = |values: &[f64]| {
+            values
+                .iter()
+                .map(f64::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        format!(
+            "IESNA:LM-63-2002\n[TEST] test\n[MANUFAC] none\nTILT={}\n\
+             1 1000 {} 2 {} 1 1 0 0 0\n1 1 50\n0 90\n{}\n{}\n",
+            tilt,
+            multiplier,
+            horizontal.len(),
+            join(horizontal),
+            join(candela)
+        )
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1.0e-9,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_tilt() {
+        let profile = IesProfile::parse(&file("NONE", 1.0, &[0.0], &[100.0, 50.0])).unwrap();
+        assert_close(profile.candela(0.0, 0.0), 100.0);
+        assert_close(profile.candela(45.0, 123.0), 75.0);
+        assert_close(profile.candela(90.0, 300.0), 50.0);
+        assert_close(profile.candela(120.0, 0.0), 0.0);
+        assert_close(profile.max_candela(), 100.0);
+    }
+
+    #[test]
+    fn included_tilt() {
+        let text = file("INCLUDE", 1.0, &[0.0], &[100.0, 50.0])
+            .replace("TILT=INCLUDE\n", "TILT=INCLUDE\n1\n3\n0 45 90\n1 0.8 0.5\n");
+        let profile = IesProfile::parse(&text).unwrap();
+        assert_close(profile.candela(0.0, 0.0), 100.0);
+    }
+
+    #[test]
+    fn multiplier() {
+        let text = file("NONE", 2.5, &[0.0], &[100.0, 50.0]).replace("1 1 50", "0.8 1 50");
+        let profile = IesProfile::parse(&text).unwrap();
+        assert_close(profile.candela(0.0, 0.0), 200.0);
+        assert_close(profile.max_candela(), 200.0);
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n1 1000 1").is_err());
+        assert!(IesProfile::parse(&file("NONE", 1.0, &[0.0], &[100.0])).is_err());
+        let text = file("NONE", 1.0, &[0.0], &[100.0, 50.0]).replace("50\n", "5O\n");
+        assert!(IesProfile::parse(&text).is_err());
+        assert!(IesProfile::parse(&file("NONE", 1.0, &[90.0, 0.0], &[1.0; 4])).is_err());
+    }
+
+    #[test]
+    fn quadrant_symmetry() {
+        let profile =
+            IesProfile::parse(&file("NONE", 1.0, &[0.0, 90.0], &[10.0, 10.0, 30.0, 30.0])).unwrap();
+        for (horizontal, expected) in [
+            (0.0, 10.0),
+            (45.0, 20.0),
+            (90.0, 30.0),
+            (135.0, 20.0),
+            (180.0, 10.0),
+            (225.0, 20.0),
+            (270.0, 30.0),
+            (315.0, 20.0),
+            (359.0, 10.0 + 20.0 / 90.0),
+        ] {
+            assert_close(profile.candela(0.0, horizontal), expected);
+        }
+    }
+
+    #[test]
+    fn bilateral_symmetry() {
+        let profile = IesProfile::parse(&file(
+            "NONE",
+            1.0,
+            &[0.0, 90.0, 180.0],
+            &[10.0, 10.0, 20.0, 20.0, 40.0, 40.0],
+        ))
+        .unwrap();
+        assert_close(profile.candela(0.0, 135.0), 30.0);
+        assert_close(profile.candela(0.0, 225.0), 30.0);
+        assert_close(profile.candela(0.0, 270.0), 20.0);
+        assert_close(profile.candela(0.0, 315.0), 15.0);
+
+        let profile = IesProfile::parse(&file(
+            "NONE",
+            1.0,
+            &[90.0, 180.0, 270.0],
+            &[10.0, 10.0, 20.0, 20.0, 40.0, 40.0],
+        ))
+        .unwrap();
+        assert_close(profile.candela(0.0, 0.0), 20.0);
+        assert_close(profile.candela(0.0, 45.0), 15.0);
+        assert_close(profile.candela(0.0, 315.0), 30.0);
+        assert_close(profile.candela(0.0, 225.0), 30.0);
+    }
+
+    #[test]
+    fn wraps_around() {
+        // Without a measurement at 360 degrees, which would repeat the one at 0
+        let profile = IesProfile::parse(&file(
+            "NONE",
+            1.0,
+            &[0.0, 90.0, 180.0, 270.0],
+            &[10.0, 10.0, 20.0, 20.0, 30.0, 30.0, 50.0, 50.0],
+        ))
+        .unwrap();
+        assert_close(profile.candela(0.0, 315.0), 30.0);
+        assert_close(profile.candela(0.0, 359.999), 10.0 + 40.0 * 0.001 / 90.0);
+        assert_close(profile.candela(0.0, 0.0), 10.0);
+        assert_close(profile.candela(0.0, -45.0), 30.0);
+    }
+}
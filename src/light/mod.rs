@@ -0,0 +1,31 @@
+mod directional;
+mod goniometric;
+mod ies;
+mod point;
+mod spot;
+mod tree;
+
+pub use directional::DirectionalLight;
+pub use goniometric::GoniometricLight;
+pub use ies::IesProfile;
+pub use point::PointLight;
+pub use spot::SpotLight;
+pub use tree::LightTree;
+
+use crate::{color::Color, point3::Point3, vec3::Vec3};
+
+/// Light arriving at a point from a sampled direction
+pub struct LightSample {
+    /// Unit vector from the point towards the light
+    pub direction: Vec3,
+    /// The distance to the light, which may be infinite
+    pub distance: f64,
+    /// The radiance arriving at the point, divided by the probability of the sample
+    pub radiance: Color,
+}
+
+/// A light source that is sampled explicitly at each shading point, rather than being found by
+/// scattered rays
+pub trait Light: Send + Sync {
+    fn sample(&self, point: Point3) -> Option<LightSample>;
+}
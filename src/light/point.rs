@@ -0,0 +1,62 @@
+use crate::{color::Color, point3::Point3};
+
+use super::{Light, LightSample};
+
+/// An infinitesimally small light emitting equally in all directions
+pub struct PointLight {
+    position: Point3,
+    /// Radiant intensity, the power per unit solid angle
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+        if distance <= 0.0 {
+            // The falloff is unbounded at the light itself
+            return None;
+        }
+
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            // Inverse-square falloff
+            radiance: (1.0 / distance.powi(2)) * self.intensity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_square_falloff() {
+        let light = PointLight::new(Point3::new(0.0, 4.0, 0.0), Color::new(8.0, 16.0, 32.0));
+
+        let sample = light.sample(Point3::new(0.0, 0.0, 0.0)).unwrap();
+        assert!((sample.distance - 4.0).abs() < 1.0e-12);
+        assert!((sample.direction.y() - 1.0).abs() < 1.0e-12);
+        assert!((sample.radiance[0] - 0.5).abs() < 1.0e-12);
+        assert!((sample.radiance[2] - 2.0).abs() < 1.0e-12);
+
+        let sample = light.sample(Point3::new(0.0, 2.0, 0.0)).unwrap();
+        assert!((sample.radiance[0] - 2.0).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn nothing_at_the_light() {
+        let light = PointLight::new(Point3::new(1.0, 2.0, 3.0), Color::new(1.0, 1.0, 1.0));
+        assert!(light.sample(Point3::new(1.0, 2.0, 3.0)).is_none());
+    }
+}
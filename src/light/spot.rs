@@ -0,0 +1,102 @@
+use crate::{color::Color, point3::Point3, vec3::Vec3};
+
+use super::{Light, LightSample};
+
+/// A point light emitting only within a cone, fading out smoothly towards its edge
+pub struct SpotLight {
+    position: Point3,
+    /// Unit vector along the axis of the cone
+    direction: Vec3,
+    intensity: Color,
+    cos_cone_angle: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    /// `cone_angle` is the angle in degrees between the axis and the edge of the cone, and the
+    /// light starts fading out at `falloff_start` degrees from the axis
+    pub fn new(
+        position: Point3,
+        look_at: Point3,
+        intensity: Color,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: (look_at - position).normalized(),
+            intensity,
+            cos_cone_angle: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(cone_angle).to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_cone_angle {
+            return 0.0;
+        }
+
+        // Smoothstep between the edge of the cone and the start of the falloff
+        let t = (cos_theta - self.cos_cone_angle) / (self.cos_falloff_start - self.cos_cone_angle);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+        if distance <= 0.0 {
+            // The falloff is unbounded at the light itself
+            return None;
+        }
+        let direction = to_light / distance;
+
+        let falloff = self.falloff(((-1.0) * direction).dot(self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: (falloff / distance.powi(2)) * self.intensity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_out_towards_the_edge() {
+        let light = SpotLight::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            45.0,
+            30.0,
+        );
+        let radiance = |angle: f64| {
+            let point = Point3::new(angle.to_radians().tan(), 0.0, 0.0);
+            let distance = (point - Point3::new(0.0, 1.0, 0.0)).length();
+            light
+                .sample(point)
+                .map_or(0.0, |sample| sample.radiance[0] * distance.powi(2))
+        };
+
+        assert!((radiance(0.0) - 1.0).abs() < 1.0e-12);
+        assert!((radiance(29.0) - 1.0).abs() < 1.0e-12);
+        let edge = radiance(37.5);
+        assert!(edge > 0.0 && edge < 1.0, "{}", edge);
+        assert!(radiance(50.0) == 0.0);
+
+        // Nothing behind the light, or at the light itself
+        assert!(light.sample(Point3::new(0.0, 2.0, 0.0)).is_none());
+        assert!(light.sample(Point3::new(0.0, 1.0, 0.0)).is_none());
+    }
+}
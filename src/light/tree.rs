@@ -0,0 +1,345 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{hit::Hit, point3::Point3, vec3::Vec3};
+
+/// A bounding volume hierarchy over the emissive objects of a world, which picks one of them for
+/// a shading point with a probability proportional to an estimate of its contribution there
+/// (Conty Estevez and Kulla 2018).
+pub struct LightTree {
+    nodes: Vec<Node>,
+    /// The path from the root to the leaf of each emitter, indexed by its object index, with one
+    /// bit per level that is set where the path goes to the second child
+    trails: Vec<Option<(u64, usize)>>,
+}
+
+struct Node {
+    bounds: LightBounds,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    /// An emitter, by its index in the world
+    Leaf(usize),
+    /// The first child directly follows its parent, the second one is at this index
+    Interior(usize),
+}
+
+/// Conservative bounds of where a group of emitters is, which way it faces and how much it emits
+#[derive(Clone, Copy)]
+struct LightBounds {
+    min: Point3,
+    max: Point3,
+    power: f64,
+    /// Axis of a cone containing all surface normals
+    axis: Vec3,
+    /// Cosine of the spread of the normals around the axis
+    cos_theta_o: f64,
+}
+
+impl LightTree {
+    pub fn new(objects: &[Box<dyn Hit>]) -> LightTree {
+        let mut emitters = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            let power = object.power();
+            let Some((min, max)) = object.bounding_box() else {
+                continue;
+            };
+            if power <= 0.0 {
+                continue;
+            }
+
+            let (axis, cos_theta_o) = object.normal_cone();
+            let bounds = LightBounds {
+                min,
+                max,
+                power,
+                axis,
+                cos_theta_o,
+            };
+            emitters.push((index, bounds));
+        }
+
+        let mut tree = LightTree {
+            nodes: Vec::with_capacity(2 * emitters.len()),
+            trails: (0..objects.len()).map(|_| None).collect(),
+        };
+        if !emitters.is_empty() {
+            tree.build(&mut emitters, 0, 0);
+        }
+
+        tree
+    }
+
+    /// Builds the subtree over `emitters` by splitting them at the median of their centers along
+    /// the axis with the largest extent
+    fn build(&mut self, emitters: &mut [(usize, LightBounds)], trail: u64, depth: usize) {
+        let bounds = emitters
+            .iter()
+            .skip(1)
+            .fold(emitters[0].1, |bounds, (_, other)| bounds.union(other));
+
+        if let [(index, _)] = emitters {
+            self.trails[*index] = Some((trail, depth));
+            self.nodes.push(Node {
+                bounds,
+                kind: NodeKind::Leaf(*index),
+            });
+            return;
+        }
+
+        let extent = bounds.max - bounds.min;
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap_or(0);
+        emitters.sort_by(|(_, a), (_, b)| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            kind: NodeKind::Interior(0),
+        });
+
+        // Deeper trees than the trail can hold would need billions of emitters
+        let (first, second) = emitters.split_at_mut(emitters.len() / 2);
+        self.build(first, trail, depth + 1);
+        let second_child = self.nodes.len();
+        self.build(second, trail | (1 << depth.min(63)), depth + 1);
+
+        self.nodes[node].kind = NodeKind::Interior(second_child);
+    }
+
+    /// Picks an emitter for shading `point`, returning its index in the world and the probability
+    /// of having picked it
+    pub fn sample(&self, point: Point3) -> Option<(usize, f64)> {
+        let mut rng = rand::thread_rng();
+        let mut node = 0;
+        let mut probability = 1.0;
+
+        loop {
+            match self.nodes.get(node)?.kind {
+                NodeKind::Leaf(index) => {
+                    return Some((index, probability));
+                }
+                NodeKind::Interior(second) => {
+                    let first_importance = self.nodes[node + 1].bounds.importance(point);
+                    let second_importance = self.nodes[second].bounds.importance(point);
+                    let total = first_importance + second_importance;
+                    if total <= 0.0 {
+                        return None;
+                    }
+
+                    let p_first = first_importance / total;
+                    if rng.gen::<f64>() < p_first {
+                        node += 1;
+                        probability *= p_first;
+                    } else {
+                        node = second;
+                        probability *= 1.0 - p_first;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The probability of `sample` picking the emitter with the given index for `point`
+    pub fn probability(&self, point: Point3, index: usize) -> f64 {
+        let Some(&Some((trail, depth))) = self.trails.get(index) else {
+            return 0.0;
+        };
+
+        let mut node = 0;
+        let mut probability = 1.0;
+        for level in 0..depth {
+            let NodeKind::Interior(second) = self.nodes[node].kind else {
+                break;
+            };
+
+            let first_importance = self.nodes[node + 1].bounds.importance(point);
+            let second_importance = self.nodes[second].bounds.importance(point);
+            let total = first_importance + second_importance;
+            if total <= 0.0 {
+                return 0.0;
+            }
+
+            if trail & (1 << level.min(63)) == 0 {
+                node += 1;
+                probability *= first_importance / total;
+            } else {
+                node = second;
+                probability *= second_importance / total;
+            }
+        }
+
+        probability
+    }
+}
+
+impl LightBounds {
+    fn centroid(&self) -> Point3 {
+        self.min + 0.5 * (self.max - self.min)
+    }
+
+    fn union(&self, other: &LightBounds) -> LightBounds {
+        let min = Point3::new(
+            self.min.x().min(other.min.x()),
+            self.min.y().min(other.min.y()),
+            self.min.z().min(other.min.z()),
+        );
+        let max = Point3::new(
+            self.max.x().max(other.max.x()),
+            self.max.y().max(other.max.y()),
+            self.max.z().max(other.max.z()),
+        );
+        let (axis, cos_theta_o) = union_cones(
+            (self.axis, self.cos_theta_o),
+            (other.axis, other.cos_theta_o),
+        );
+
+        LightBounds {
+            min,
+            max,
+            power: self.power + other.power,
+            axis,
+            cos_theta_o,
+        }
+    }
+
+    /// An estimate of how much the emitters contribute at `point`: their power over the squared
+    /// distance, times the largest cosine at which any of their surfaces could face the point
+    fn importance(&self, point: Point3) -> f64 {
+        let to_point = point - self.centroid();
+        let distance = to_point.length();
+        let radius = 0.5 * (self.max - self.min).length();
+
+        // Don't let the distance go to zero for points inside the bounds
+        let distance2 = distance.powi(2).max(0.25 * radius.powi(2));
+
+        // Angle between the cone's axis and the direction to the point
+        let cos_theta_w = if distance > 0.0 {
+            self.axis.dot(to_point / distance).clamp(-1.0, 1.0)
+        } else {
+            1.0
+        };
+        let sin_theta_w = (1.0 - cos_theta_w.powi(2)).max(0.0).sqrt();
+
+        // Angle subtended by the bounds as seen from the point
+        let (sin_theta_b, cos_theta_b) = if distance <= radius {
+            (0.0, -1.0)
+        } else {
+            let sin = radius / distance;
+            (sin, (1.0 - sin.powi(2)).max(0.0).sqrt())
+        };
+
+        // The smallest angle between any normal in the cone and any direction towards the point,
+        // max(0, theta_w - theta_o - theta_b)
+        let sin_theta_o = (1.0 - self.cos_theta_o.powi(2)).max(0.0).sqrt();
+        let (sin_theta_x, cos_theta_x) =
+            subtract_clamped((sin_theta_w, cos_theta_w), (sin_theta_o, self.cos_theta_o));
+        let (_, cos_theta_p) =
+            subtract_clamped((sin_theta_x, cos_theta_x), (sin_theta_b, cos_theta_b));
+
+        // Diffuse emitters only emit into the hemisphere around their normal
+        if cos_theta_p <= 0.0 {
+            return 0.0;
+        }
+
+        self.power * cos_theta_p / distance2
+    }
+}
+
+/// Sine and cosine of the difference of two angles, given by their sines and cosines, clamped to
+/// be at least zero
+fn subtract_clamped((sin_a, cos_a): (f64, f64), (sin_b, cos_b): (f64, f64)) -> (f64, f64) {
+    if cos_a > cos_b {
+        (0.0, 1.0)
+    } else {
+        (sin_a * cos_b - cos_a * sin_b, cos_a * cos_b + sin_a * sin_b)
+    }
+}
+
+/// The smallest cone containing two cones, given by their axes and the cosines of their spreads
+fn union_cones((axis_a, cos_a): (Vec3, f64), (axis_b, cos_b): (Vec3, f64)) -> (Vec3, f64) {
+    let theta_a = cos_a.clamp(-1.0, 1.0).acos();
+    let theta_b = cos_b.clamp(-1.0, 1.0).acos();
+    let theta_d = axis_a.dot(axis_b).clamp(-1.0, 1.0).acos();
+
+    // One of the cones might already contain the other
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return (axis_a, cos_a);
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return (axis_b, cos_b);
+    }
+
+    let theta_o = 0.5 * (theta_a + theta_d + theta_b);
+    let rotation_axis = axis_a.cross(axis_b);
+    if theta_o >= PI || rotation_axis.near_zero() {
+        return (axis_a, -1.0);
+    }
+
+    // Rotate the first axis towards the second one (Rodrigues' rotation formula)
+    let k = rotation_axis.normalized();
+    let theta_r = theta_o - theta_a;
+    let axis = theta_r.cos() * axis_a
+        + theta_r.sin() * k.cross(axis_a)
+        + ((1.0 - theta_r.cos()) * k.dot(axis_a)) * k;
+
+    (axis.normalized(), theta_o.cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::color::Color;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::sphere::Sphere;
+
+    fn light(center: Point3, radius: f64, brightness: f64) -> Box<dyn Hit> {
+        let emit = Color::new(brightness, brightness, brightness);
+        Box::new(Sphere::new(
+            center,
+            radius,
+            Arc::new(DiffuseLight::new(emit)),
+        ))
+    }
+
+    #[test]
+    fn probability_matches_sampling() {
+        let objects: Vec<Box<dyn Hit>> = vec![
+            light(Point3::new(-4.0, 2.0, 0.0), 0.5, 4.0),
+            Box::new(Sphere::new(
+                Point3::new(0.0, 0.0, 0.0),
+                1.0,
+                Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            )),
+            light(Point3::new(1.0, 3.0, 1.0), 1.0, 1.0),
+            light(Point3::new(6.0, 1.0, -2.0), 0.3, 10.0),
+            light(Point3::new(0.0, 5.0, 5.0), 0.2, 2.0),
+            light(Point3::new(2.0, -1.0, 3.0), 0.7, 0.5),
+        ];
+        let tree = LightTree::new(&objects);
+        let point = Point3::new(0.5, 1.5, 0.5);
+
+        let samples = 200_000;
+        let mut counts = vec![0; objects.len()];
+        for _ in 0..samples {
+            let (index, probability) = tree.sample(point).unwrap();
+            assert!((probability - tree.probability(point, index)).abs() < 1.0e-12);
+            counts[index] += 1;
+        }
+
+        let mut total = 0.0;
+        for (index, count) in counts.into_iter().enumerate() {
+            let probability = tree.probability(point, index);
+            let frequency = count as f64 / samples as f64;
+            assert!((frequency - probability).abs() < 0.005, "{}", index);
+            total += probability;
+        }
+        assert!((total - 1.0).abs() < 1.0e-12);
+        assert_eq!(tree.probability(point, 1), 0.0);
+    }
+}
@@ -2,135 +2,393 @@ use rayon::iter::ParallelIterator;
 use std::{
     fs::File,
     io::{stdout, Write},
-    sync::Arc,
 };
 
+mod alpha_mask;
 mod camera;
 mod color;
+mod density_grid;
+mod environment;
+mod heterogeneous_medium;
 mod hit;
+mod light;
 mod material;
+mod mesh;
+mod onb;
 mod point3;
 mod ray;
+mod scene;
 mod sphere;
+mod subsurface;
+mod texture;
 mod vec3;
 
-use crate::{
-    material::{Dielectric, Lambertian, Metal},
-    vec3::Vec3,
+use crate::vec3::Vec3;
+use camera::{
+    Aperture, ApertureMask, Camera, Convergence, CubeMap, Equirectangular, Fisheye,
+    FisheyeProjection, LensSystem, Orthographic, Perspective, Realistic, Stereo, StereoLayout,
 };
-use camera::Camera;
 use color::Color;
-use hit::{Hit, World};
+use hit::{Hit, HitRecord, World};
 use point3::Point3;
 use rand::Rng;
 use ray::Ray;
 use rayon::iter::IntoParallelIterator;
-use sphere::Sphere;
+use texture::ImageTexture;
 
-fn ray_color(ray: &Ray, world: &World, depth: u64) -> Color {
-    if depth <= 0 {
+/// `scatter_pdf` is the probability density of the material that scattered `ray` choosing its
+/// direction, or zero for camera rays and materials with an unknown density
+fn ray_color(ray: &Ray, world: &World, depth: u64, scatter_pdf: f64) -> Color {
+    if depth == 0 {
         // If we've exceeded the ray bounce limit, no more light is gathered
         return Color::new(0.0, 0.0, 0.0);
     }
 
-    if let Some(record) = world.hit(ray, 0.001, f64::INFINITY) {
+    if let Some((index, record)) = world.hit_object(ray, 0.001, f64::INFINITY) {
+        let mut emitted = record.material.emitted(&record);
+        if scatter_pdf > 0.0 && !emitted.is_black() {
+            // Weight against the chance of having sampled this point in `sample_lights`
+            let light_pdf = world.emitter_pdf(ray.origin(), index, &record);
+            emitted = power_heuristic(scatter_pdf, light_pdf) * emitted;
+        }
+
+        let direct = emitted + sample_lights(ray, &record, world);
+
         if let Some((attenuation, scattered)) = record.material.scatter(ray, &record) {
-            attenuation * ray_color(&scattered, world, depth - 1)
+            let pdf = record
+                .material
+                .pdf(ray, &record, scattered.direction().normalized());
+            direct + attenuation * ray_color(&scattered, world, depth - 1, pdf)
         } else {
-            Color::new(0.0, 0.0, 0.0)
+            direct
         }
     } else {
-        let unit_direction = ray.direction().normalized();
-        let t = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+        let direction = ray.direction().normalized();
+        let environment = world.environment();
+
+        // Weight against the chance of having sampled this direction in `sample_lights`
+        let weight = if scatter_pdf > 0.0 {
+            power_heuristic(scatter_pdf, environment.pdf(direction))
+        } else {
+            1.0
+        };
+
+        weight * environment.radiance(direction)
     }
 }
 
-fn random_scene() -> World {
-    let mut rng = rand::thread_rng();
-    let mut world = World::new();
-
-    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
-    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
-
-    world.push(Box::new(ground_sphere));
-
-    for a in -11..=11 {
-        for b in -11..=11 {
-            let choose_mat: f64 = rng.gen();
-            let center = Point3::new(
-                (a as f64) + rng.gen_range(0.0..0.9),
-                0.2,
-                (b as f64) + rng.gen_range(0.0..0.9),
-            );
-
-            if choose_mat < 0.8 {
-                // Diffuse
-                let albedo = Color::random(0.0..1.0) * Color::random(0.0..1.0);
-                let sphere_mat = Arc::new(Lambertian::new(albedo));
-                let sphere = Sphere::new(center, 0.2, sphere_mat);
-
-                world.push(Box::new(sphere));
-            } else if choose_mat < 0.95 {
-                // Metal
-                let albedo = Color::random(0.4..1.0);
-                let fuzz = rng.gen_range(0.0..0.5);
-                let sphere_mat = Arc::new(Metal::new(albedo, fuzz));
-                let sphere = Sphere::new(center, 0.2, sphere_mat);
-
-                world.push(Box::new(sphere));
-            } else {
-                // Glass
-                let sphere_mat = Arc::new(Dielectric::new(1.5));
-                let sphere = Sphere::new(center, 0.2, sphere_mat);
-
-                world.push(Box::new(sphere));
+/// Builds the world of a scene
+type Scene = fn() -> World;
+
+/// The scenes that can be chosen by the first argument, the first one being the default
+const SCENES: &[(&str, Scene)] = &[
+    ("random", scene::random),
+    ("dispersion", scene::dispersion),
+    ("colored_glass", scene::colored_glass),
+    ("microfacet", scene::microfacet),
+    ("principled", scene::principled),
+    ("anisotropic", scene::anisotropic),
+    ("oren_nayar", scene::oren_nayar),
+    ("thin_film", scene::thin_film),
+    ("layered", scene::layered),
+    ("weathered", scene::weathered),
+    ("normal_map", || scene::normal_map(std::env::args().nth(2))),
+    ("alpha_mask", scene::alpha_mask),
+    ("subsurface", scene::subsurface),
+    ("cloud", || scene::cloud(std::env::args().nth(2))),
+    ("lights", scene::lights),
+    ("environment_map", || {
+        scene::environment_map(std::env::args().nth(2))
+    }),
+    ("sky", || scene::sky(std::env::args().nth(2))),
+    ("many_lights", scene::many_lights),
+    ("mesh_light", scene::mesh_light),
+    ("ies", || scene::ies(std::env::args().nth(2))),
+];
+
+/// Where the scenes are seen from, shared by all cameras
+struct View {
+    look_from: Point3,
+    look_at: Point3,
+    v_up: Vec3,
+    aspect_ratio: f64,
+    focus_dist: f64,
+    aperture: f64,
+    /// Distance between the eyes of stereo cameras
+    interocular: f64,
+}
+
+/// Builds a camera looking at the scene from a view, together with the aspect ratio of the image
+/// it takes
+type MakeCamera = fn(&View) -> (Box<dyn Camera>, f64);
+
+/// The cameras that can be chosen by the CAMERA environment variable, the first one being the
+/// default
+const CAMERAS: &[(&str, MakeCamera)] = &[
+    ("perspective", |view| {
+        (Box::new(perspective(view)), view.aspect_ratio)
+    }),
+    // About as much of the scene as the perspective camera shows at its focus distance
+    ("orthographic", |view| {
+        let camera = Orthographic::new(
+            view.look_from,
+            view.look_at,
+            view.v_up,
+            3.5,
+            view.aspect_ratio,
+        );
+        (Box::new(camera), view.aspect_ratio)
+    }),
+    // A wide open lens with a hexagonal aperture, or one shaped like the image at the path in the
+    // APERTURE_MASK environment variable
+    ("bokeh", |view| {
+        let aperture = match std::env::var("APERTURE_MASK") {
+            Ok(path) => {
+                let mask = ImageTexture::load(&path)
+                    .and_then(|image| ApertureMask::new(&image))
+                    .unwrap_or_else(|e| panic!("Failed to load aperture mask {}: {}", path, e));
+                Aperture::Mask(mask)
             }
+            Err(_) => Aperture::Polygon {
+                blades: 6,
+                rotation: 15.0,
+            },
+        };
+        let camera = Perspective::new(
+            view.look_from,
+            view.look_at,
+            view.v_up,
+            20.0,
+            view.aspect_ratio,
+            1.0,
+            view.focus_dist,
+        )
+        .with_aperture(aperture)
+        .with_vignetting(0.6);
+
+        (Box::new(camera), view.aspect_ratio)
+    }),
+    // A 50mm lens on full frame film, or the lens in the file at the path in the LENS environment
+    // variable
+    ("lens", |view| {
+        let lens = match std::env::var("LENS") {
+            Ok(path) => LensSystem::load(&path)
+                .unwrap_or_else(|e| panic!("Failed to load lens {}: {}", path, e)),
+            Err(_) => LensSystem::double_gauss(),
+        };
+        let camera = Realistic::new(
+            view.look_from,
+            view.look_at,
+            view.v_up,
+            lens,
+            43.3,
+            view.aspect_ratio,
+            view.focus_dist,
+        )
+        .unwrap_or_else(|e| panic!("Failed to focus the lens: {}", e));
+
+        (Box::new(camera), view.aspect_ratio)
+    }),
+    // Level, with the lens shifted down to keep vertical lines parallel, and a wide open lens
+    // tilted so only a band across the ground is in focus, like a miniature
+    ("tilt_shift", |view| {
+        let level = Point3::new(view.look_at.x(), view.look_from.y(), view.look_at.z());
+        let camera = Perspective::new(
+            view.look_from,
+            level,
+            view.v_up,
+            20.0,
+            view.aspect_ratio,
+            0.6,
+            view.focus_dist,
+        )
+        .with_shift(0.0, -0.25)
+        .with_tilt(-30.0, 0.0);
+
+        (Box::new(camera), view.aspect_ratio)
+    }),
+    ("equirectangular", |view| {
+        let camera = Equirectangular::new(view.look_from, view.look_at, view.v_up);
+        (Box::new(camera), 2.0)
+    }),
+    ("fisheye", |view| {
+        let camera = Fisheye::new(
+            view.look_from,
+            view.look_at,
+            view.v_up,
+            180.0,
+            1.0,
+            FisheyeProjection::Equidistant,
+        );
+        (Box::new(camera), 1.0)
+    }),
+    ("equisolid_fisheye", |view| {
+        let camera = Fisheye::new(
+            view.look_from,
+            view.look_at,
+            view.v_up,
+            180.0,
+            view.aspect_ratio,
+            FisheyeProjection::Equisolid,
+        );
+        (Box::new(camera), view.aspect_ratio)
+    }),
+    ("cube_map", |view| {
+        (Box::new(CubeMap::new(view.look_from)), 1.5)
+    }),
+    // Side by side, each eye with the usual aspect ratio
+    ("stereo", |view| {
+        (stereo(view, Convergence::OffAxis), 2.0 * view.aspect_ratio)
+    }),
+    ("stereo_toe_in", |view| {
+        (stereo(view, Convergence::ToeIn), 2.0 * view.aspect_ratio)
+    }),
+    // Omni-directional stereo
+    ("stereo_panorama", |view| {
+        let panorama = || Equirectangular::new(view.look_from, view.look_at, view.v_up);
+        let left = panorama().with_eye_offset(-view.interocular / 2.0);
+        let right = panorama().with_eye_offset(view.interocular / 2.0);
+        let stereo = Stereo::new(Box::new(left), Box::new(right), StereoLayout::TopBottom);
+
+        (Box::new(stereo), 1.0)
+    }),
+];
+
+/// The default camera, with a narrow field of view and a bit of depth of field
+fn perspective(view: &View) -> Perspective {
+    Perspective::new(
+        view.look_from,
+        view.look_at,
+        view.v_up,
+        20.0,
+        view.aspect_ratio,
+        view.aperture,
+        view.focus_dist,
+    )
+}
+
+/// A pair of default cameras side by side, converging as given
+fn stereo(view: &View, convergence: Convergence) -> Box<dyn Camera> {
+    let left = perspective(view).with_eye_offset(-view.interocular / 2.0, convergence);
+    let right = perspective(view).with_eye_offset(view.interocular / 2.0, convergence);
+
+    Box::new(Stereo::new(
+        Box::new(left),
+        Box::new(right),
+        StereoLayout::SideBySide,
+    ))
+}
+
+/// Exits listing the valid choices for a `kind` of option, after an unknown `name` was chosen
+fn unknown<'a>(kind: &str, name: &str, valid: impl Iterator<Item = &'a str>) -> ! {
+    eprintln!("Unknown {}: {}", kind, name);
+    eprintln!(
+        "Valid {}s are: {}",
+        kind,
+        valid.collect::<Vec<_>>().join(", ")
+    );
+    std::process::exit(1);
+}
+
+/// Light arriving directly from the world's light sources, emissive objects and environment
+fn sample_lights(ray: &Ray, record: &HitRecord, world: &World) -> Color {
+    let mut direct = Color::new(0.0, 0.0, 0.0);
+
+    for light in world.lights() {
+        let Some(sample) = light.sample(record.point) else {
+            continue;
+        };
+
+        let f = record.material.eval(ray, record, sample.direction);
+        if f.is_black() {
+            continue;
+        }
+
+        // Cast a shadow ray towards the light
+        let shadow_ray = Ray::with_wavelength(record.point, sample.direction, ray.wavelength());
+        let transmittance = world.transmittance(&shadow_ray, 0.001, sample.distance - 0.001);
+
+        direct += f * transmittance * sample.radiance;
+    }
+
+    // Emissive objects and the environment can also be found by scattered rays, so combine both
+    // strategies with multiple importance sampling, which isn't possible if the material's
+    // density is unknown
+    if let Some((light_record, light_pdf)) = world.sample_emitter(record.point) {
+        let to_light = light_record.point - record.point;
+        let distance = to_light.length();
+        let direction = to_light / distance;
+
+        let scatter_pdf = record.material.pdf(ray, record, direction);
+        let emitted = light_record.material.emitted(&light_record);
+        if scatter_pdf > 0.0 && !emitted.is_black() {
+            let f = record.material.eval(ray, record, direction);
+            let shadow_ray = Ray::with_wavelength(record.point, direction, ray.wavelength());
+            let transmittance = world.transmittance(&shadow_ray, 0.001, distance - 0.001);
+            let weight = power_heuristic(light_pdf, scatter_pdf) / light_pdf;
+
+            direct += weight * f * transmittance * emitted;
         }
     }
 
-    let mat1 = Arc::new(Dielectric::new(1.5));
-    let mat2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
-    let mat3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    let environment = world.environment();
+    if let Some((direction, light_pdf)) = environment.sample() {
+        let scatter_pdf = record.material.pdf(ray, record, direction);
+        if scatter_pdf > 0.0 {
+            let f = record.material.eval(ray, record, direction);
+            let shadow_ray = Ray::with_wavelength(record.point, direction, ray.wavelength());
+            let transmittance = world.transmittance(&shadow_ray, 0.001, f64::INFINITY);
+            let weight = power_heuristic(light_pdf, scatter_pdf) / light_pdf;
 
-    let sphere1 = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1);
-    let sphere2 = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2);
-    let sphere3 = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3);
+            direct += weight * f * transmittance * environment.radiance(direction);
+        }
+    }
 
-    world.push(Box::new(sphere1));
-    world.push(Box::new(sphere2));
-    world.push(Box::new(sphere3));
+    direct
+}
 
-    world
+/// Veach's power heuristic for weighting a sample taken with density `pdf` against another
+/// sampling strategy with density `other_pdf`
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    pdf.powi(2) / (pdf.powi(2) + other_pdf.powi(2))
 }
 
 fn main() {
     // Image
     const ASPECT_RATIO: f64 = 16.0 / 9.0;
     const IMAGE_WIDTH: u64 = 1200;
-    const IMAGE_HEIGHT: u64 = ((IMAGE_WIDTH as f64) / ASPECT_RATIO) as u64;
     const SAMPLES_PER_PIXEL: u64 = 500;
     const MAX_DEPTH: u64 = 50;
 
     // World
-    let world = random_scene();
+    let name = std::env::args().nth(1);
+    let name = name.as_deref().unwrap_or(SCENES[0].0);
+    let Some((_, scene)) = SCENES.iter().find(|(scene, _)| *scene == name) else {
+        unknown("scene", name, SCENES.iter().map(|(name, _)| *name));
+    };
+    let world = scene();
 
     // Camera
-    let lookfrom = Point3::new(13.0, 2.0, 3.0);
-    let lookat = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let dist_to_focus = 10.0;
-    let aperture = 0.1;
-
-    let camera = Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        20.0,
-        ASPECT_RATIO,
-        aperture,
-        dist_to_focus,
-    );
+    let view = View {
+        look_from: Point3::new(13.0, 2.0, 3.0),
+        look_at: Point3::new(0.0, 0.0, 0.0),
+        v_up: Vec3::new(0.0, 1.0, 0.0),
+        aspect_ratio: ASPECT_RATIO,
+        focus_dist: 10.0,
+        aperture: 0.1,
+        // Exaggerated for the scale of the scenes
+        interocular: 0.4,
+    };
+
+    // Chosen by the CAMERA environment variable, as the arguments already select the scene.
+    // Panoramas bring their own aspect ratio.
+    let name = std::env::var("CAMERA");
+    let name = name.as_deref().unwrap_or(CAMERAS[0].0);
+    let Some((_, camera)) = CAMERAS.iter().find(|(camera, _)| *camera == name) else {
+        unknown("camera", name, CAMERAS.iter().map(|(name, _)| *name));
+    };
+    let (camera, aspect_ratio) = camera(&view);
+    let image_height = ((IMAGE_WIDTH as f64) / aspect_ratio) as u64;
 
     let filename = "image.ppm";
     match std::fs::remove_file(filename) {
@@ -141,10 +399,10 @@ fn main() {
     let mut file = File::create(filename).unwrap();
 
     writeln!(file, "P3").unwrap();
-    writeln!(file, "{IMAGE_WIDTH} {IMAGE_HEIGHT}").unwrap();
+    writeln!(file, "{IMAGE_WIDTH} {image_height}").unwrap();
     writeln!(file, "255").unwrap();
 
-    for j in (0..IMAGE_HEIGHT).rev() {
+    for j in (0..image_height).rev() {
         print!("\rScanlines remaining: {:3}", j);
         stdout().flush().unwrap();
 
@@ -158,10 +416,18 @@ fn main() {
                     let random_v: f64 = rng.gen();
 
                     let u = ((i as f64) + random_u) / ((IMAGE_WIDTH - 1) as f64);
-                    let v = ((j as f64) + random_v) / ((IMAGE_HEIGHT - 1) as f64);
+                    let v = ((j as f64) + random_v) / ((image_height - 1) as f64);
+
+                    // Rays the camera blocks contribute nothing
+                    let Some(r) = camera.get_ray(u, v) else {
+                        continue;
+                    };
 
-                    let r = camera.get_ray(u, v);
-                    pixel_color += ray_color(&r, &world, MAX_DEPTH);
+                    // Cameras with dispersive lenses already split the light up by wavelength
+                    let weight = r
+                        .wavelength()
+                        .map_or(Color::new(1.0, 1.0, 1.0), Color::from_wavelength);
+                    pixel_color += weight * ray_color(&r, &world, MAX_DEPTH, 0.0);
                 }
 
                 pixel_color
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use crate::{color::Color, hit::HitRecord, ray::Ray, texture::Texture, vec3::Vec3};
+
+use super::Scatter;
+
+/// The offset in surface coordinates used to estimate the slope of the height map
+const DELTA: f64 = 1.0e-3;
+
+/// Perturbs the shading normal of another material by the slope of a scalar height map, taken
+/// from the luminance of a texture
+pub struct BumpMap {
+    base: Arc<dyn Scatter>,
+    height: Arc<dyn Texture>,
+    /// The height of the bumps in world units for a texture luminance of one
+    scale: f64,
+}
+
+impl BumpMap {
+    pub fn new(base: Arc<dyn Scatter>, height: Arc<dyn Texture>, scale: f64) -> BumpMap {
+        BumpMap {
+            base,
+            height,
+            scale,
+        }
+    }
+
+    /// The hit record with the bumped shading normal
+    fn shading(&self, r_in: &Ray, rec: &HitRecord) -> HitRecord {
+        // Forward differences in the surface coordinates, moving the point along with them so that
+        // solid textures see the same offset
+        let height = |du: f64, dv: f64| {
+            let p = rec.point + du * rec.dpdu + dv * rec.dpdv;
+            self.scale * self.height.value(rec.u + du, rec.v + dv, p).luminance()
+        };
+        let h = height(0.0, 0.0);
+        let dhdu = (height(DELTA, 0.0) - h) / DELTA;
+        let dhdv = (height(0.0, DELTA) - h) / DELTA;
+
+        // The normal of the surface displaced along its normal by the height (Blinn 1978)
+        let dpdu = rec.dpdu + dhdu * rec.normal;
+        let dpdv = rec.dpdv + dhdv * rec.normal;
+        let mut normal = dpdu.cross(dpdv);
+        if normal.dot(rec.normal) < 0.0 {
+            normal = (-1.0) * normal;
+        }
+
+        // Keep the geometric normal where the bumped one would face away from the viewer
+        if normal.near_zero() || normal.dot(r_in.direction()) >= 0.0 {
+            return rec.clone();
+        }
+
+        rec.with_normal(normal)
+    }
+}
+
+impl Scatter for BumpMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.base.scatter(r_in, &self.shading(r_in, rec))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        self.base.eval(r_in, &self.shading(r_in, rec), direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        self.base.pdf(r_in, &self.shading(r_in, rec), direction)
+    }
+}
@@ -2,19 +2,27 @@ use rand::Rng;
 
 use crate::{color::Color, hit::HitRecord, ray::Ray};
 
-use super::Scatter;
+use super::{Scatter, Substrate};
 
 pub struct Dielectric {
     index_of_refraction: f64,
+    /// Absorption coefficient σₐ per unit of length inside the medium, so that light travelling
+    /// a distance `d` is attenuated by `exp(-σₐ·d)` (Beer-Lambert)
+    absorption: Color,
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f64) -> Dielectric {
         Dielectric {
             index_of_refraction,
+            absorption: Color::new(0.0, 0.0, 0.0),
         }
     }
 
+    pub fn with_absorption(self, absorption: Color) -> Dielectric {
+        Dielectric { absorption, ..self }
+    }
+
     fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
         // Use Schlick's approximation for reflectance
         let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)).powi(2);
@@ -44,8 +52,53 @@ impl Scatter for Dielectric {
             unit_direction.refract(rec.normal, refraction_ratio)
         };
 
-        let scattered = Ray::new(rec.point, direction);
+        let scattered = Ray::with_wavelength(rec.point, direction, r_in.wavelength());
+
+        let attenuation = if rec.front_face {
+            Color::new(1.0, 1.0, 1.0)
+        } else {
+            // The ray travelled through the medium, so apply the Beer-Lambert law
+            let distance = rec.t * r_in.direction().length();
+            (-distance * self.absorption).exp()
+        };
+
+        Some((attenuation, scattered))
+    }
+
+    fn substrate(&self) -> Option<Substrate> {
+        Some(Substrate::Dielectric(self.index_of_refraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::hit::Hit;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
 
-        Some((Color::new(1.0, 1.0, 1.0), scattered))
+    #[test]
+    fn beer_lambert_attenuation() {
+        let glass = Arc::new(Dielectric::new(1.5).with_absorption(Color::new(0.5, 1.0, 2.0)));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, glass.clone());
+
+        // Leaving the sphere after travelling its radius, with a direction that isn't normalized
+        let inside = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.5, 0.0, 0.0));
+        let rec = sphere.hit(&inside, 0.001, f64::INFINITY).unwrap();
+        let (attenuation, _) = glass.scatter(&inside, &rec).unwrap();
+        for (channel, sigma) in [0.5_f64, 1.0, 2.0].into_iter().enumerate() {
+            assert!((attenuation[channel] - (-sigma).exp()).abs() < 1.0e-12);
+        }
+
+        // Entering it isn't attenuated
+        let outside = Ray::new(Point3::new(-2.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let rec = sphere.hit(&outside, 0.001, f64::INFINITY).unwrap();
+        let (attenuation, _) = glass.scatter(&outside, &rec).unwrap();
+        for channel in 0..3 {
+            assert_eq!(attenuation[channel], 1.0);
+        }
     }
 }
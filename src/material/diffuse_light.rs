@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use crate::{
+    color::Color,
+    hit::HitRecord,
+    ray::Ray,
+    texture::{SolidColor, Texture},
+};
+
+use super::Scatter;
+
+/// A surface that emits light equally in all directions from its front side, without reflecting
+/// any
+pub struct DiffuseLight {
+    /// Emitted radiance
+    emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> DiffuseLight {
+        Self::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn textured(emit: Arc<dyn Texture>) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Scatter for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Ray)> {
+        None
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        if rec.front_face {
+            self.emit.value(rec.u, rec.v, rec.point)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use rand::Rng;
+
+use crate::{
+    color::{Color, WAVELENGTH_MAX, WAVELENGTH_MIN},
+    hit::HitRecord,
+    ray::Ray,
+};
+
+use super::{Dielectric, Scatter, Substrate};
+
+/// Describes how the index of refraction of a material varies with wavelength
+#[derive(Clone, Copy)]
+pub enum Dispersion {
+    /// Cauchy's equation `n = a + b / λ²` with λ in micrometers
+    Cauchy { a: f64, b: f64 },
+    /// Sellmeier equation `n² = 1 + Σ b_i λ² / (λ² - c_i)` with λ in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// Schott N-BK7 borosilicate crown glass
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+
+    /// Fused silica (Malitson 1965)
+    pub const FUSED_SILICA: Dispersion = Dispersion::Sellmeier {
+        b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        c: [0.004_679_148_26, 0.013_512_063_1, 97.934_002_5],
+    };
+
+    /// Diamond (Peter 1923)
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier {
+        b: [0.3306, 4.3356, 0.0],
+        c: [0.030_625, 0.011_236, 0.0],
+    };
+
+    /// The index of refraction at the given wavelength in nanometers
+    pub fn index_of_refraction(&self, wavelength: f64) -> f64 {
+        let lambda = wavelength / 1000.0;
+        let lambda_sq = lambda.powi(2);
+
+        match *self {
+            Dispersion::Cauchy { a, b } => a + b / lambda_sq,
+            Dispersion::Sellmeier { b, c } => (1.0
+                + b.iter()
+                    .zip(c.iter())
+                    .map(|(b, c)| b * lambda_sq / (lambda_sq - c))
+                    .sum::<f64>())
+            .sqrt(),
+        }
+    }
+}
+
+pub struct DispersiveDielectric {
+    dispersion: Dispersion,
+    absorption: Color,
+}
+
+impl DispersiveDielectric {
+    pub fn new(dispersion: Dispersion) -> DispersiveDielectric {
+        DispersiveDielectric {
+            dispersion,
+            absorption: Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn with_absorption(self, absorption: Color) -> DispersiveDielectric {
+        DispersiveDielectric { absorption, ..self }
+    }
+}
+
+impl Scatter for DispersiveDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        // A ray that hasn't been split up yet picks a single wavelength and carries it from here on
+        let (wavelength, weight) = match r_in.wavelength() {
+            Some(wavelength) => (wavelength, Color::new(1.0, 1.0, 1.0)),
+            None => {
+                let wavelength = rand::thread_rng().gen_range(WAVELENGTH_MIN..WAVELENGTH_MAX);
+                (wavelength, Color::from_wavelength(wavelength))
+            }
+        };
+
+        let r_in = Ray::with_wavelength(r_in.origin(), r_in.direction(), Some(wavelength));
+        let dielectric = Dielectric::new(self.dispersion.index_of_refraction(wavelength))
+            .with_absorption(self.absorption);
+        let (attenuation, scattered) = dielectric.scatter(&r_in, rec)?;
+
+        Some((weight * attenuation, scattered))
+    }
+
+    fn substrate(&self) -> Option<Substrate> {
+        // Films don't follow the dispersion, so use the index at the d line
+        Some(Substrate::Dielectric(
+            self.dispersion.index_of_refraction(587.6),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bk7_at_d_line() {
+        let ior = Dispersion::BK7.index_of_refraction(587.6);
+        assert!((ior - 1.5168).abs() < 1.0e-4, "n_d = {}", ior);
+    }
+
+    #[test]
+    fn cauchy_decreases_with_wavelength() {
+        let glass = Dispersion::Cauchy { a: 1.5, b: 0.004 };
+        assert!(glass.index_of_refraction(450.0) > glass.index_of_refraction(650.0));
+    }
+}
@@ -0,0 +1,67 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{color::Color, hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3};
+
+use super::Scatter;
+
+/// The Henyey-Greenstein phase function of a participating medium, which favors forward
+/// scattering for a positive asymmetry `g` and backward scattering for a negative one
+pub struct HenyeyGreenstein {
+    albedo: Color,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Color, g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            albedo,
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    fn phase(&self, r_in: &Ray, direction: Vec3) -> f64 {
+        let cos_theta = r_in.direction().normalized().dot(direction);
+        let g = self.g;
+
+        (1.0 - g.powi(2)) / (4.0 * PI * (1.0 + g.powi(2) - 2.0 * g * cos_theta).powf(1.5))
+    }
+}
+
+impl Scatter for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let mut rng = rand::thread_rng();
+        let xi: f64 = rng.gen();
+
+        // Invert the CDF of the scattering angle relative to the direction of travel
+        let cos_theta = if self.g.abs() < 1.0e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let g = self.g;
+            let square = (1.0 - g.powi(2)) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g.powi(2) - square.powi(2)) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta.powi(2)).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+
+        let frame = Onb::from_w(r_in.direction());
+        let direction = frame.to_world(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+        let scattered = Ray::with_wavelength(rec.point, direction, r_in.wavelength());
+
+        Some((self.albedo, scattered))
+    }
+
+    fn eval(&self, r_in: &Ray, _rec: &HitRecord, direction: Vec3) -> Color {
+        self.phase(r_in, direction) * self.albedo
+    }
+
+    fn pdf(&self, r_in: &Ray, _rec: &HitRecord, direction: Vec3) -> f64 {
+        // The phase function is sampled exactly
+        self.phase(r_in, direction)
+    }
+}
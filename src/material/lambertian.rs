@@ -1,3 +1,5 @@
+use std::f64::consts::PI;
+
 use crate::{color::Color, hit::HitRecord, ray::Ray, vec3::Vec3};
 
 use super::Scatter;
@@ -13,15 +15,23 @@ impl Lambertian {
 }
 
 impl Scatter for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
         let mut scatter_direction = rec.normal + Vec3::random_in_unit_sphere().normalized();
         if scatter_direction.near_zero() {
             // Catch degenerate scatter direction
             scatter_direction = rec.normal;
         }
 
-        let scattered = Ray::new(rec.point, scatter_direction);
+        let scattered = Ray::with_wavelength(rec.point, scatter_direction, r_in.wavelength());
 
         Some((self.albedo, scattered))
     }
+
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        (rec.normal.dot(direction).max(0.0) / PI) * self.albedo
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        rec.normal.dot(direction).max(0.0) / PI
+    }
 }
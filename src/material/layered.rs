@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{color::Color, hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3};
+
+use super::{
+    microfacet::{fresnel_dielectric, TrowbridgeReitz},
+    Scatter,
+};
+
+/// The number of times light may bounce between the coat and the base before it's discarded
+const MAX_BOUNCES: usize = 16;
+
+/// A dielectric coat over an arbitrary base material, like the clearcoat of car paint.
+///
+/// Light is traced through the layers stochastically: it either reflects off the coat or enters
+/// it, gets absorbed on its way through, scatters off the base and then either leaves through the
+/// coat or is reflected back down towards the base.
+pub struct Layered {
+    base: Arc<dyn Scatter>,
+    coat_ior: f64,
+    coat: TrowbridgeReitz,
+    /// Absorption coefficient of the coat per unit of length, attenuating light by
+    /// `exp(-absorption · thickness / cos θ)` on each pass through it
+    absorption: Color,
+    thickness: f64,
+}
+
+impl Layered {
+    pub fn new(base: Arc<dyn Scatter>, coat_ior: f64, coat_roughness: f64) -> Layered {
+        Layered {
+            base,
+            coat_ior,
+            coat: TrowbridgeReitz::from_roughness(coat_roughness),
+            absorption: Color::new(0.0, 0.0, 0.0),
+            thickness: 0.0,
+        }
+    }
+
+    /// Tints the coat, following the Beer-Lambert law for a coat of the given thickness
+    pub fn with_absorption(self, absorption: Color, thickness: f64) -> Layered {
+        Layered {
+            absorption,
+            thickness,
+            ..self
+        }
+    }
+
+    /// Transmittance through the coat along a direction with the given cosine to the normal
+    fn transmittance(&self, cos_theta: f64) -> Color {
+        (-(self.thickness / cos_theta.abs()) * self.absorption).exp()
+    }
+}
+
+impl Scatter for Layered {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        if !rec.front_face {
+            // The coat only covers the outside of the surface
+            return self.base.scatter(r_in, rec);
+        }
+
+        let mut rng = rand::thread_rng();
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+
+        // Reflect off or refract into the coat
+        let m = self.coat.sample_visible_normal(wo);
+        let reflectance = fresnel_dielectric(wo.dot(m), self.coat_ior);
+        let shadowing = |wo: Vec3, wi: Vec3| self.coat.g(wo, wi) / self.coat.g1(wo);
+
+        if rng.gen::<f64>() < reflectance {
+            let wi = ((-1.0) * wo).reflect(m);
+            if wi.z() <= 0.0 {
+                return None;
+            }
+
+            let weight = shadowing(wo, wi);
+            let scattered = Ray::with_wavelength(rec.point, frame.to_world(wi), r_in.wavelength());
+            return Some((Color::new(weight, weight, weight), scattered));
+        }
+
+        let mut down = ((-1.0) * wo).refract(m, 1.0 / self.coat_ior);
+        if down.z() >= 0.0 {
+            return None;
+        }
+        let mut throughput = shadowing(wo, down) * Color::new(1.0, 1.0, 1.0);
+
+        for _ in 0..MAX_BOUNCES {
+            // Travel down through the coat and scatter off the base
+            throughput = throughput * self.transmittance(down.z());
+
+            let r_base = Ray::with_wavelength(rec.point, frame.to_world(down), r_in.wavelength());
+            let (attenuation, scattered) = self.base.scatter(&r_base, rec)?;
+            throughput = throughput * attenuation;
+
+            let up = frame.to_local(scattered.direction().normalized());
+            if up.z() <= 0.0 {
+                // Transmitted through or absorbed by the base
+                return None;
+            }
+            throughput = throughput * self.transmittance(up.z());
+
+            // Seen from inside the coat, the interface is upside down
+            let flip = |v: Vec3| Vec3::new(v.x(), v.y(), -v.z());
+            let wo_inside = flip((-1.0) * up);
+            let m = self.coat.sample_visible_normal(wo_inside);
+            let reflectance = fresnel_dielectric(wo_inside.dot(m), 1.0 / self.coat_ior);
+
+            if rng.gen::<f64>() < reflectance {
+                // Internal reflection back towards the base
+                let wi = ((-1.0) * wo_inside).reflect(m);
+                if wi.z() <= 0.0 {
+                    return None;
+                }
+
+                throughput = shadowing(wo_inside, wi) * throughput;
+                down = flip(wi);
+            } else {
+                let wi = ((-1.0) * wo_inside).refract(m, self.coat_ior);
+                if wi.z() >= 0.0 {
+                    return None;
+                }
+
+                throughput = shadowing(wo_inside, wi) * throughput;
+                let scattered =
+                    Ray::with_wavelength(rec.point, frame.to_world(flip(wi)), r_in.wavelength());
+                return Some((throughput, scattered));
+            }
+        }
+
+        None
+    }
+
+    /// Single scattering through the coat: the specular reflection off its top, plus the light
+    /// that refracts straight down to the base and back up without bouncing inside the coat
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        if !rec.front_face {
+            return self.base.eval(r_in, rec, direction);
+        }
+
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let wi = frame.to_local(direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let specular = match self.coat.eval_reflection(wo, wi) {
+            Some((h, value)) => fresnel_dielectric(wo.dot(h), self.coat_ior) * value,
+            None => 0.0,
+        };
+
+        // Directions inside the coat, where the base sees them
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let down = ((-1.0) * wo).refract(normal, 1.0 / self.coat_ior);
+        let up = (-1.0) * ((-1.0) * wi).refract(normal, 1.0 / self.coat_ior);
+        let transmitted = (1.0 - fresnel_dielectric(wo.z(), self.coat_ior))
+            * (1.0 - fresnel_dielectric(wi.z(), self.coat_ior));
+
+        let r_base = Ray::with_wavelength(rec.point, frame.to_world(down), r_in.wavelength());
+        let base = self.base.eval(&r_base, rec, frame.to_world(up))
+            * self.transmittance(down.z())
+            * self.transmittance(up.z());
+
+        // The cosine at the base is relative to the refracted direction, so rescale it to the
+        // cosine of the outgoing direction and account for the compression of the solid angle
+        let scale = transmitted * wi.z() / (up.z() * self.coat_ior.powi(2)).max(1.0e-4);
+
+        Color::new(specular, specular, specular) + scale * base
+    }
+
+    /// The random walk through the layers has no closed form density, and `eval` leaves out the
+    /// light bouncing inside the coat, so emissive objects and the environment are only found by
+    /// scattered rays. Lights that can't be hit are still shaded with `eval`.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hit::Hit;
+    use crate::material::Lambertian;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+
+    /// The fraction of the light arriving straight down at the top of a sphere that `material`
+    /// scatters back
+    fn albedo(material: Arc<dyn Scatter>) -> f64 {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material.clone());
+        let r_in = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        let samples = 20000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            if let Some((attenuation, scattered)) = material.scatter(&r_in, &rec) {
+                assert!(scattered.direction().dot(rec.normal) > 0.0);
+                assert!(attenuation[0] <= 1.0 + 1.0e-9);
+                total += attenuation[0];
+            }
+        }
+
+        total / samples as f64
+    }
+
+    #[test]
+    fn coat_over_black_reflects_its_fresnel() {
+        let black = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)));
+        let albedo = albedo(Arc::new(Layered::new(black, 1.5, 0.0)));
+        assert!((albedo - 0.04).abs() < 0.01, "{}", albedo);
+    }
+
+    #[test]
+    fn clear_coat_conserves_energy() {
+        // Only the light shadowed by the microfacets of the coat is lost
+        let white = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
+        let clear = albedo(Arc::new(Layered::new(white.clone(), 1.5, 0.3)));
+        assert!(clear > 0.85 && clear <= 1.0, "{}", clear);
+
+        let tinted = albedo(Arc::new(
+            Layered::new(white, 1.5, 0.3).with_absorption(Color::new(1.0, 1.0, 1.0), 0.5),
+        ));
+        assert!(tinted < 0.5 * clear, "{} {}", tinted, clear);
+    }
+}
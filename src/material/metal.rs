@@ -1,30 +1,204 @@
-use crate::{color::Color, hit::HitRecord, ray::Ray, vec3::Vec3};
+use crate::{color::Color, hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3};
 
-use super::Scatter;
+use super::{microfacet::TrowbridgeReitz, Scatter, Substrate};
+
+enum Roughness {
+    /// Perturbs the mirror direction by a random offset inside a sphere of this radius.
+    ///
+    /// The density of the perturbed directions isn't known, so these metals aren't lit directly
+    /// by lights that can't be hit, like point and spot lights, and only reflect the ones that
+    /// scattered rays find.
+    Fuzz(f64),
+    /// A GGX distribution stretched along the surface's tangent, rotated by the given angle in
+    /// degrees
+    Anisotropic {
+        distribution: TrowbridgeReitz,
+        rotation: f64,
+    },
+}
 
 pub struct Metal {
     albedo: Color,
-    fuzz: f64,
+    roughness: Roughness,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Metal {
-        Metal { albedo, fuzz }
+        Metal {
+            albedo,
+            roughness: Roughness::Fuzz(fuzz),
+        }
+    }
+
+    /// A brushed metal with roughness `alpha_x` along the tangent and `alpha_y` along the
+    /// bitangent of the surface
+    pub fn anisotropic(albedo: Color, alpha_x: f64, alpha_y: f64, rotation: f64) -> Metal {
+        Metal {
+            albedo,
+            roughness: Roughness::Anisotropic {
+                distribution: TrowbridgeReitz::new(alpha_x, alpha_y),
+                rotation,
+            },
+        }
+    }
+
+    /// The shading frame with the x-axis along the rotated direction of anisotropy
+    fn frame(rec: &HitRecord, rotation: f64) -> Onb {
+        let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+        let tangent = cos_phi * rec.tangent + sin_phi * rec.bitangent;
+        Onb::new(tangent, rec.normal.cross(tangent), rec.normal)
+    }
+
+    /// Schlick's approximation with the albedo as reflectance at normal incidence
+    fn fresnel(&self, cos_theta: f64) -> Color {
+        let weight = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5);
+        (1.0 - weight) * self.albedo + weight * Color::new(1.0, 1.0, 1.0)
     }
 }
 
 impl Scatter for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let reflected = r_in.direction().reflect(rec.normal).normalized();
-        let scattered = Ray::new(
-            rec.point,
-            reflected + self.fuzz * Vec3::random_in_unit_sphere(),
-        );
-
-        if scattered.direction().dot(rec.normal) > 0.0 {
-            Some((self.albedo, scattered))
-        } else {
-            None
+        match self.roughness {
+            Roughness::Fuzz(fuzz) => {
+                let reflected = r_in.direction().reflect(rec.normal).normalized();
+                let scattered = Ray::with_wavelength(
+                    rec.point,
+                    reflected + fuzz * Vec3::random_in_unit_sphere(),
+                    r_in.wavelength(),
+                );
+
+                if scattered.direction().dot(rec.normal) > 0.0 {
+                    Some((self.albedo, scattered))
+                } else {
+                    None
+                }
+            }
+            Roughness::Anisotropic {
+                distribution,
+                rotation,
+            } => {
+                let frame = Metal::frame(rec, rotation);
+
+                let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+                if wo.z() <= 0.0 {
+                    return None;
+                }
+
+                let m = distribution.sample_visible_normal(wo);
+                let wi = ((-1.0) * wo).reflect(m);
+                if wi.z() <= 0.0 {
+                    return None;
+                }
+
+                let fresnel = self.fresnel(wo.dot(m));
+                let shadowing = distribution.g(wo, wi) / distribution.g1(wo);
+
+                let scattered =
+                    Ray::with_wavelength(rec.point, frame.to_world(wi), r_in.wavelength());
+
+                Some((shadowing * fresnel, scattered))
+            }
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        match self.roughness {
+            // A perfect or fuzzed mirror has no density that could be evaluated, so it gets no
+            // direct lighting
+            Roughness::Fuzz(_) => Color::new(0.0, 0.0, 0.0),
+            Roughness::Anisotropic {
+                distribution,
+                rotation,
+            } => {
+                let frame = Metal::frame(rec, rotation);
+                let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+                let wi = frame.to_local(direction);
+
+                match distribution.eval_reflection(wo, wi) {
+                    Some((h, value)) => value * self.fresnel(wo.dot(h)),
+                    None => Color::new(0.0, 0.0, 0.0),
+                }
+            }
         }
     }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        match self.roughness {
+            Roughness::Fuzz(_) => 0.0,
+            Roughness::Anisotropic {
+                distribution,
+                rotation,
+            } => {
+                let frame = Metal::frame(rec, rotation);
+                let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+
+                distribution.reflection_pdf(wo, frame.to_local(direction))
+            }
+        }
+    }
+
+    fn substrate(&self) -> Option<Substrate> {
+        // Fit a complex index of refraction to the albedo as reflectance at normal incidence,
+        // with the edge tint taken to be the same (Gulbrandsen 2014)
+        let mut eta = Color::new(0.0, 0.0, 0.0);
+        let mut k = Color::new(0.0, 0.0, 0.0);
+        for channel in 0..3 {
+            let r = self.albedo[channel].clamp(0.0, 0.99);
+            let g = r;
+            let n = g * (1.0 - r) / (1.0 + r) + (1.0 - g) * (1.0 + r.sqrt()) / (1.0 - r.sqrt());
+            eta[channel] = n;
+            k[channel] = ((r * (n + 1.0).powi(2) - (n - 1.0).powi(2)) / (1.0 - r))
+                .max(0.0)
+                .sqrt();
+        }
+
+        Some(Substrate::Conductor { eta, k })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::hit::Hit;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+
+    /// The average squared offset of the reflections of a ray straight down onto the top of a
+    /// sphere, along the x- and the z-axis
+    fn spread(metal: Metal) -> (f64, f64) {
+        let metal = Arc::new(metal);
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, metal.clone());
+        let r_in = Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        let samples = 10000;
+        let (mut x, mut z) = (0.0, 0.0);
+        for _ in 0..samples {
+            let Some((attenuation, scattered)) = metal.scatter(&r_in, &rec) else {
+                continue;
+            };
+            assert!(attenuation[0] <= 1.0);
+            let direction = scattered.direction().normalized();
+            x += direction.x().powi(2);
+            z += direction.z().powi(2);
+        }
+
+        (x / samples as f64, z / samples as f64)
+    }
+
+    #[test]
+    fn stretched_along_the_tangent() {
+        // The tangent at the top of a sphere points along the x-axis
+        let white = Color::new(1.0, 1.0, 1.0);
+        let (x, z) = spread(Metal::anisotropic(white, 0.5, 0.02, 0.0));
+        assert!(x > 20.0 * z, "{} {}", x, z);
+
+        let (x, z) = spread(Metal::anisotropic(white, 0.5, 0.02, 90.0));
+        assert!(z > 20.0 * x, "{} {}", x, z);
+
+        let (x, z) = spread(Metal::anisotropic(white, 0.3, 0.3, 0.0));
+        assert!((x - z).abs() < 0.2 * (x + z), "{} {}", x, z);
+    }
 }
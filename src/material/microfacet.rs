@@ -0,0 +1,260 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{color::Color, vec3::Vec3};
+
+/// The GGX/Trowbridge-Reitz microfacet distribution with Smith masking-shadowing.
+///
+/// All directions are expressed in the local shading frame, where the normal is the z-axis.
+#[derive(Clone, Copy)]
+pub struct TrowbridgeReitz {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> TrowbridgeReitz {
+        // Perfectly smooth surfaces are numerically unstable, so keep a tiny bit of roughness
+        TrowbridgeReitz {
+            alpha_x: alpha_x.max(1.0e-3),
+            alpha_y: alpha_y.max(1.0e-3),
+        }
+    }
+
+    /// Maps a perceptually linear roughness in `[0, 1]` to the distribution's alpha
+    pub fn from_roughness(roughness: f64) -> TrowbridgeReitz {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        TrowbridgeReitz::new(alpha, alpha)
+    }
+
+    /// Density of microfacets oriented along `m`
+    pub fn d(&self, m: Vec3) -> f64 {
+        if m.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let cos2_theta = m.z().powi(2);
+        let e = ((m.x() / self.alpha_x).powi(2) + (m.y() / self.alpha_y).powi(2)) / cos2_theta;
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos2_theta.powi(2) * (1.0 + e).powi(2))
+    }
+
+    fn lambda(&self, w: Vec3) -> f64 {
+        let alpha2_tan2 =
+            ((self.alpha_x * w.x()).powi(2) + (self.alpha_y * w.y()).powi(2)) / w.z().powi(2);
+        0.5 * (-1.0 + (1.0 + alpha2_tan2).sqrt())
+    }
+
+    /// Fraction of microfacets visible from direction `w`
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both `wo` and `wi`
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Probability density of `sample_visible_normal` returning `m`
+    pub fn visible_normal_pdf(&self, wo: Vec3, m: Vec3) -> f64 {
+        self.g1(wo) * wo.dot(m).max(0.0) * self.d(m) / wo.z()
+    }
+
+    /// The half vector between `wo` and `wi`, and `D G / (4 cos_o)`, which is the BSDF times the
+    /// cosine of `wi` for reflection apart from the Fresnel term
+    pub fn eval_reflection(&self, wo: Vec3, wi: Vec3) -> Option<(Vec3, f64)> {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return None;
+        }
+
+        let h = (wo + wi).normalized();
+        Some((h, self.d(h) * self.g(wo, wi) / (4.0 * wo.z())))
+    }
+
+    /// The half vector between `wo` and `wi`, and the BSDF times the cosine of `wi` for
+    /// refraction apart from the Fresnel term, where `eta` is the ratio of the index of refraction
+    /// on the side of `wi` over the one on the side of `wo`
+    pub fn eval_transmission(&self, wo: Vec3, wi: Vec3, eta: f64) -> Option<(Vec3, f64)> {
+        let h = Self::transmission_half_vector(wo, wi, eta)?;
+        let cos_o_h = wo.dot(h);
+        let cos_i_h = wi.dot(h);
+
+        let value = eta.powi(2) * self.d(h) * self.g(wo, wi) * cos_o_h * cos_i_h.abs()
+            / (wo.z() * (cos_o_h + eta * cos_i_h).powi(2));
+
+        Some((h, value))
+    }
+
+    /// Probability density of reflecting `wo` about a sampled visible normal into `wi`
+    pub fn reflection_pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (wo + wi).normalized();
+        self.visible_normal_pdf(wo, h) / (4.0 * wo.dot(h))
+    }
+
+    /// Probability density of refracting `wo` through a sampled visible normal into `wi`
+    pub fn transmission_pdf(&self, wo: Vec3, wi: Vec3, eta: f64) -> f64 {
+        let Some(h) = Self::transmission_half_vector(wo, wi, eta) else {
+            return 0.0;
+        };
+        let cos_o_h = wo.dot(h);
+        let cos_i_h = wi.dot(h);
+
+        // The change of variables from the microfacet normal to the refracted direction
+        let jacobian = eta.powi(2) * cos_i_h.abs() / (cos_o_h + eta * cos_i_h).powi(2);
+
+        self.visible_normal_pdf(wo, h) * jacobian
+    }
+
+    /// The microfacet normal that refracts `wo` into `wi`, if there is one facing both
+    fn transmission_half_vector(wo: Vec3, wi: Vec3, eta: f64) -> Option<Vec3> {
+        if wo.z() <= 0.0 || wi.z() >= 0.0 {
+            return None;
+        }
+
+        let mut h = (wo + eta * wi).normalized();
+        if h.z() < 0.0 {
+            h = (-1.0) * h;
+        }
+
+        if wo.dot(h) <= 0.0 || wi.dot(h) >= 0.0 {
+            return None;
+        }
+
+        Some(h)
+    }
+
+    /// Samples a microfacet normal proportional to its visible area from `wo` (Heitz 2018)
+    pub fn sample_visible_normal(&self, wo: Vec3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let u1: f64 = rng.gen();
+        let u2: f64 = rng.gen();
+
+        // Transform the view direction to the hemisphere configuration
+        let vh = Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).normalized();
+
+        // Orthonormal basis around the view direction
+        let len_sq = vh.x().powi(2) + vh.y().powi(2);
+        let t1 = if len_sq > 0.0 {
+            Vec3::new(-vh.y(), vh.x(), 0.0) / len_sq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        // Sample the projected area of the visible hemisphere
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z());
+        let p2 = (1.0 - s) * (1.0 - p1.powi(2)).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1.powi(2) - p2.powi(2)).max(0.0).sqrt() * vh;
+
+        // Transform the normal back to the ellipsoid configuration
+        Vec3::new(
+            self.alpha_x * nh.x(),
+            self.alpha_y * nh.y(),
+            nh.z().max(1.0e-6),
+        )
+        .normalized()
+    }
+}
+
+/// Unpolarized Fresnel reflectance at a dielectric interface, where `eta` is the ratio of the
+/// index of refraction on the transmitted side over the incident side
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_t = (1.0 - cos_theta_i.powi(2)) / eta.powi(2);
+    if sin2_theta_t >= 1.0 {
+        // Total internal reflection
+        return 1.0;
+    }
+
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let r_s = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    let r_p = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+
+    0.5 * (r_s.powi(2) + r_p.powi(2))
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index of refraction `eta + i k`
+pub fn fresnel_conductor(cos_theta_i: f64, eta: Color, k: Color) -> Color {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_theta_i.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+
+        let t0 = eta.powi(2) - k.powi(2) - sin2;
+        let a2_plus_b2 = (t0.powi(2) + 4.0 * eta.powi(2) * k.powi(2)).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let r_s = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2.powi(2);
+        let t4 = t2 * sin2;
+        let r_p = r_s * (t3 - t4) / (t3 + t4);
+
+        0.5 * (r_s + r_p)
+    };
+
+    Color::new(
+        channel(eta[0], k[0]),
+        channel(eta[1], k[1]),
+        channel(eta[2], k[2]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dielectric_fresnel() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1.0e-12);
+        assert!((fresnel_dielectric(1.0, 1.0 / 1.5) - 0.04).abs() < 1.0e-12);
+        assert_eq!(fresnel_dielectric(0.0, 1.5), 1.0);
+
+        // Beyond the critical angle inside the glass
+        assert_eq!(fresnel_dielectric(0.5, 1.0 / 1.5), 1.0);
+    }
+
+    #[test]
+    fn conductor_without_absorption_is_a_dielectric() {
+        let eta = Color::new(1.2, 1.5, 2.0);
+        for cos_theta in [0.05, 0.3, 0.7, 1.0] {
+            let reflectance = fresnel_conductor(cos_theta, eta, Color::new(0.0, 0.0, 0.0));
+            for channel in 0..3 {
+                let expected = fresnel_dielectric(cos_theta, eta[channel]);
+                assert!((reflectance[channel] - expected).abs() < 1.0e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn visible_normals_face_the_viewer() {
+        for distribution in [
+            TrowbridgeReitz::new(0.3, 0.3),
+            TrowbridgeReitz::new(0.05, 0.8),
+        ] {
+            for wo in [
+                Vec3::new(0.0, 0.0, 1.0),
+                Vec3::new(0.6, 0.0, 0.8),
+                Vec3::new(-0.5, 0.7, 0.1).normalized(),
+            ] {
+                for _ in 0..1000 {
+                    let m = distribution.sample_visible_normal(wo);
+                    assert!((m.length() - 1.0).abs() < 1.0e-9);
+                    assert!(m.z() > 0.0 && wo.dot(m) > -1.0e-9);
+                }
+            }
+        }
+
+        // Smooth surfaces have all microfacets facing up
+        let m = TrowbridgeReitz::new(0.0, 0.0).sample_visible_normal(Vec3::new(0.6, 0.0, 0.8));
+        assert!(m.z() > 0.99);
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    color::Color,
+    hit::HitRecord,
+    ray::Ray,
+    texture::{SolidColor, Texture},
+    vec3::Vec3,
+};
+
+use super::Scatter;
+
+/// Blends two materials by stochastically picking one of them at each hit
+pub struct MixMaterial {
+    first: Arc<dyn Scatter>,
+    second: Arc<dyn Scatter>,
+    /// The fraction of the second material, taken from the texture's luminance
+    weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new(first: Arc<dyn Scatter>, second: Arc<dyn Scatter>, weight: f64) -> MixMaterial {
+        let weight = Color::new(weight, weight, weight);
+        Self::textured(first, second, Arc::new(SolidColor::new(weight)))
+    }
+
+    pub fn textured(
+        first: Arc<dyn Scatter>,
+        second: Arc<dyn Scatter>,
+        weight: Arc<dyn Texture>,
+    ) -> MixMaterial {
+        MixMaterial {
+            first,
+            second,
+            weight,
+        }
+    }
+}
+
+impl Scatter for MixMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let weight = self
+            .weight
+            .value(rec.u, rec.v, rec.point)
+            .luminance()
+            .clamp(0.0, 1.0);
+
+        // Picking a material with the probability of its weight makes both cancel out
+        if rand::thread_rng().gen::<f64>() < weight {
+            self.second.scatter(r_in, rec)
+        } else {
+            self.first.scatter(r_in, rec)
+        }
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let weight = self
+            .weight
+            .value(rec.u, rec.v, rec.point)
+            .luminance()
+            .clamp(0.0, 1.0);
+
+        (1.0 - weight) * self.first.eval(r_in, rec, direction)
+            + weight * self.second.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let first = self.first.pdf(r_in, rec, direction);
+        let second = self.second.pdf(r_in, rec, direction);
+
+        // If either material's density is unknown, so is the density of the mix
+        if first <= 0.0 || second <= 0.0 {
+            return 0.0;
+        }
+
+        let weight = self
+            .weight
+            .value(rec.u, rec.v, rec.point)
+            .luminance()
+            .clamp(0.0, 1.0);
+
+        (1.0 - weight) * first + weight * second
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hit::Hit;
+    use crate::material::{Lambertian, Metal, RoughConductor};
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn blends_by_weight() {
+        let red = Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let blue = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 1.0)));
+        let mix = Arc::new(MixMaterial::new(red, blue, 0.25));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mix.clone());
+        let r_in = Ray::new(Point3::new(0.0, 3.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        let samples = 20000;
+        let mut total = Color::new(0.0, 0.0, 0.0);
+        for _ in 0..samples {
+            let (attenuation, _) = mix.scatter(&r_in, &rec).unwrap();
+            total += attenuation;
+        }
+
+        let average = (1.0 / samples as f64) * total;
+        assert!((average[0] - 0.75).abs() < 0.02, "{}", average[0]);
+        assert!((average[2] - 0.25).abs() < 0.02, "{}", average[2]);
+    }
+
+    #[test]
+    fn density_is_the_weighted_sum() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let diffuse = Arc::new(Lambertian::new(white));
+        let glossy = Arc::new(RoughConductor::new(
+            Color::new(0.2, 0.4, 1.4),
+            Color::new(3.6, 2.4, 1.8),
+            0.5,
+        ));
+        let mix = Arc::new(MixMaterial::new(diffuse.clone(), glossy.clone(), 0.25));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mix.clone());
+        let r_in = Ray::new(Point3::new(1.0, 3.0, 0.0), Vec3::new(-0.5, -1.0, 0.0));
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        for _ in 0..100 {
+            let direction = Vec3::random_in_hemisphere(rec.normal).normalized();
+
+            let expected = 0.75 * diffuse.pdf(&r_in, &rec, direction)
+                + 0.25 * glossy.pdf(&r_in, &rec, direction);
+            assert!((mix.pdf(&r_in, &rec, direction) - expected).abs() < 1.0e-12);
+
+            let expected = 0.75 * diffuse.eval(&r_in, &rec, direction)
+                + 0.25 * glossy.eval(&r_in, &rec, direction);
+            let value = mix.eval(&r_in, &rec, direction);
+            for channel in 0..3 {
+                assert!((value[channel] - expected[channel]).abs() < 1.0e-12);
+            }
+        }
+
+        // A mirror has no density, and neither has a mix with it
+        let mirror = Arc::new(Metal::new(white, 0.0));
+        let mix = MixMaterial::new(diffuse, mirror, 0.25);
+        assert_eq!(mix.pdf(&r_in, &rec, rec.normal), 0.0);
+    }
+}
@@ -1,13 +1,67 @@
+mod bump_map;
 mod dielectric;
+mod diffuse_light;
+mod dispersive;
+mod henyey_greenstein;
 mod lambertian;
+mod layered;
 mod metal;
+mod microfacet;
+mod mix;
+mod normal_map;
+mod oren_nayar;
+mod principled;
+mod rough_conductor;
+mod rough_dielectric;
+mod thin_film;
 
+pub use bump_map::BumpMap;
 pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
+pub use dispersive::{Dispersion, DispersiveDielectric};
+pub use henyey_greenstein::HenyeyGreenstein;
 pub use lambertian::Lambertian;
+pub use layered::Layered;
 pub use metal::Metal;
+pub use mix::MixMaterial;
+pub use normal_map::NormalMap;
+pub use oren_nayar::OrenNayar;
+pub use principled::Principled;
+pub use rough_conductor::RoughConductor;
+pub use rough_dielectric::RoughDielectric;
+pub use thin_film::{Substrate, ThinFilm};
 
-use crate::{color::Color, hit::HitRecord, ray::Ray};
+use crate::{color::Color, hit::HitRecord, ray::Ray, vec3::Vec3};
 
 pub trait Scatter: Send + Sync {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)>;
+
+    /// The BSDF times the cosine of the angle to the normal, for light arriving from the unit
+    /// vector `direction` and leaving along the reversed direction of `r_in`.
+    ///
+    /// This is used to shade with lights that can't be hit by scattered rays, so purely specular
+    /// materials don't reflect any of them.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// The probability density with respect to solid angle of `scatter` picking `direction`.
+    ///
+    /// Zero means that the density is unknown, like for specular materials or those whose `eval`
+    /// only approximates how they scatter, in which case lights that can also be hit by scattered
+    /// rays aren't sampled explicitly for them.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// The radiance emitted from the hit point towards the origin of the ray that found it
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// The optical properties of the material's surface as seen by a thin film deposited on it,
+    /// or `None` if it has no smooth interface, like diffuse materials
+    fn substrate(&self) -> Option<Substrate> {
+        None
+    }
 }
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::{color::Color, hit::HitRecord, ray::Ray, texture::Texture, vec3::Vec3};
+
+use super::Scatter;
+
+/// Perturbs the shading normal of another material by a tangent-space normal map, where red,
+/// green and blue encode the offsets along the tangent, bitangent and normal
+pub struct NormalMap {
+    base: Arc<dyn Scatter>,
+    map: Arc<dyn Texture>,
+    /// Scales the tangential part of the mapped normals, zero leaves the surface flat
+    strength: f64,
+}
+
+impl NormalMap {
+    pub fn new(base: Arc<dyn Scatter>, map: Arc<dyn Texture>, strength: f64) -> NormalMap {
+        NormalMap {
+            base,
+            map,
+            strength,
+        }
+    }
+
+    /// The hit record with the mapped shading normal
+    fn shading(&self, r_in: &Ray, rec: &HitRecord) -> HitRecord {
+        let texel = self.map.value(rec.u, rec.v, rec.point);
+        let normal = (self.strength * (2.0 * texel[0] - 1.0)) * rec.tangent
+            + (self.strength * (2.0 * texel[1] - 1.0)) * rec.bitangent
+            + (2.0 * texel[2] - 1.0).max(0.0) * rec.normal;
+
+        // Keep the geometric normal where the mapped one would face away from the viewer
+        if normal.near_zero() || normal.dot(r_in.direction()) >= 0.0 {
+            return rec.clone();
+        }
+
+        rec.with_normal(normal)
+    }
+}
+
+impl Scatter for NormalMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        self.base.scatter(r_in, &self.shading(r_in, rec))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        self.base.eval(r_in, &self.shading(r_in, rec), direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        self.base.pdf(r_in, &self.shading(r_in, rec), direction)
+    }
+}
@@ -0,0 +1,148 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    color::Color,
+    hit::HitRecord,
+    onb::Onb,
+    ray::Ray,
+    texture::{SolidColor, Texture},
+    vec3::Vec3,
+};
+
+use super::Scatter;
+
+/// A rough diffuse surface made of V-shaped Lambertian microfacets (Oren and Nayar 1994)
+pub struct OrenNayar {
+    albedo: Arc<dyn Texture>,
+    a: f64,
+    b: f64,
+}
+
+impl OrenNayar {
+    /// `sigma` is the standard deviation of the microfacet slope angle in degrees, at zero this
+    /// is identical to `Lambertian`
+    pub fn new(albedo: Color, sigma: f64) -> OrenNayar {
+        Self::textured(Arc::new(SolidColor::new(albedo)), sigma)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, sigma: f64) -> OrenNayar {
+        let sigma2 = sigma.to_radians().powi(2);
+
+        OrenNayar {
+            albedo,
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+
+    /// The bracketed angular term of the model, which scales the Lambertian reflectance
+    fn angular_term(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let sin_theta_o = (1.0 - wo.z().powi(2)).max(0.0).sqrt();
+        let sin_theta_i = (1.0 - wi.z().powi(2)).max(0.0).sqrt();
+
+        // Cosine of the azimuthal angle between both directions
+        let cos_phi = if sin_theta_o > 1.0e-4 && sin_theta_i > 1.0e-4 {
+            ((wo.x() * wi.x() + wo.y() * wi.y()) / (sin_theta_o * sin_theta_i)).max(0.0)
+        } else {
+            0.0
+        };
+
+        // sin(alpha) * tan(beta) with alpha the larger and beta the smaller polar angle
+        let (sin_alpha, tan_beta) = if wi.z().abs() > wo.z().abs() {
+            (sin_theta_o, sin_theta_i / wi.z().abs())
+        } else {
+            (sin_theta_i, sin_theta_o / wo.z().abs().max(1.0e-4))
+        };
+
+        self.a + self.b * cos_phi * sin_alpha * tan_beta
+    }
+}
+
+impl Scatter for OrenNayar {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let wi = Vec3::random_cosine_direction();
+
+        // The cosine-weighted sampling cancels out everything but the angular term
+        let weight = self.angular_term(wo, wi);
+        let albedo = self.albedo.value(rec.u, rec.v, rec.point);
+        let scattered = Ray::with_wavelength(rec.point, frame.to_world(wi), r_in.wavelength());
+
+        Some((weight * albedo, scattered))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let wi = frame.to_local(direction);
+        if wi.z() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let albedo = self.albedo.value(rec.u, rec.v, rec.point);
+
+        (self.angular_term(wo, wi) * wi.z() / PI) * albedo
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        rec.normal.dot(direction).max(0.0) / PI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{material::Lambertian, point3::Point3};
+
+    use super::*;
+
+    #[test]
+    fn smooth_matches_lambertian() {
+        let albedo = Color::new(0.8, 0.5, 0.2);
+        let oren_nayar = OrenNayar::new(albedo, 0.0);
+        let lambertian = Arc::new(Lambertian::new(albedo));
+
+        let directions = [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.3, 0.4, 0.8),
+            Vec3::new(-0.9, 0.1, 0.2),
+            Vec3::new(0.5, -0.5, 0.05),
+        ];
+        for &incoming in &directions {
+            let r_in = Ray::new(Point3::new(0.0, 0.0, 0.0) + incoming, (-1.0) * incoming);
+            let rec = HitRecord::new(
+                Point3::new(0.0, 0.0, 0.0),
+                lambertian.clone(),
+                1.0,
+                (0.0, 0.0),
+                &r_in,
+                Vec3::new(0.0, 0.0, 1.0),
+                (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            );
+
+            // Both sample cosine-weighted directions, leaving only the albedo as the weight
+            for _ in 0..100 {
+                let (expected, _) = lambertian.scatter(&r_in, &rec).unwrap();
+                let (attenuation, scattered) = oren_nayar.scatter(&r_in, &rec).unwrap();
+                assert!(scattered.direction().dot(rec.normal) >= 0.0);
+                for channel in 0..3 {
+                    assert!((attenuation[channel] - expected[channel]).abs() < 1.0e-12);
+                }
+            }
+
+            // Including directions below the surface
+            for outgoing in directions.into_iter().chain([Vec3::new(0.2, 0.2, -0.7)]) {
+                let direction = outgoing.normalized();
+                let expected = lambertian.eval(&r_in, &rec, direction);
+                let actual = oren_nayar.eval(&r_in, &rec, direction);
+                for channel in 0..3 {
+                    assert!((expected[channel] - actual[channel]).abs() < 1.0e-12);
+                }
+
+                let expected = lambertian.pdf(&r_in, &rec, direction);
+                let actual = oren_nayar.pdf(&r_in, &rec, direction);
+                assert!((expected - actual).abs() < 1.0e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{color::Color, hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3};
+
+use super::{microfacet::TrowbridgeReitz, RoughDielectric, Scatter};
+
+/// The Disney "principled" BSDF (Burley 2012), blending a diffuse, sheen, specular, clearcoat
+/// and transmission lobe controlled by artist-friendly parameters in `[0, 1]`
+pub struct Principled {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    specular_tint: f64,
+    sheen: f64,
+    sheen_tint: f64,
+    clearcoat: f64,
+    clearcoat_gloss: f64,
+    transmission: f64,
+    index_of_refraction: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Color) -> Principled {
+        Principled {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            index_of_refraction: 1.5,
+        }
+    }
+
+    pub fn with_metallic(self, metallic: f64) -> Principled {
+        Principled { metallic, ..self }
+    }
+
+    pub fn with_roughness(self, roughness: f64) -> Principled {
+        Principled { roughness, ..self }
+    }
+
+    pub fn with_specular(self, specular: f64) -> Principled {
+        Principled { specular, ..self }
+    }
+
+    pub fn with_specular_tint(self, specular_tint: f64) -> Principled {
+        Principled {
+            specular_tint,
+            ..self
+        }
+    }
+
+    pub fn with_sheen(self, sheen: f64) -> Principled {
+        Principled { sheen, ..self }
+    }
+
+    pub fn with_sheen_tint(self, sheen_tint: f64) -> Principled {
+        Principled { sheen_tint, ..self }
+    }
+
+    pub fn with_clearcoat(self, clearcoat: f64) -> Principled {
+        Principled { clearcoat, ..self }
+    }
+
+    pub fn with_clearcoat_gloss(self, clearcoat_gloss: f64) -> Principled {
+        Principled {
+            clearcoat_gloss,
+            ..self
+        }
+    }
+
+    pub fn with_transmission(self, transmission: f64) -> Principled {
+        Principled {
+            transmission,
+            ..self
+        }
+    }
+
+    pub fn with_index_of_refraction(self, index_of_refraction: f64) -> Principled {
+        Principled {
+            index_of_refraction,
+            ..self
+        }
+    }
+
+    /// The base color normalized to unit luminance, used to tint the specular and sheen lobes
+    fn tint(&self) -> Color {
+        let luminance = self.base_color.luminance();
+        if luminance > 0.0 {
+            (1.0 / luminance) * self.base_color
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    fn specular_distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::from_roughness(self.roughness)
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        mix(0.1, 0.001, self.clearcoat_gloss)
+    }
+
+    /// Probabilities of sampling the diffuse, specular and clearcoat lobe of the opaque part
+    fn lobe_probabilities(&self) -> (f64, f64, f64) {
+        let diffuse = 1.0 - self.metallic;
+        let specular = 1.0;
+        let clearcoat = 0.25 * self.clearcoat;
+        let total = diffuse + specular + clearcoat;
+
+        (diffuse / total, specular / total, clearcoat / total)
+    }
+
+    /// The opaque part of the BSDF times the cosine of the incoming direction
+    fn eval_opaque(&self, wo: Vec3, wi: Vec3) -> Color {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let h = (wo + wi).normalized();
+        let cos_d = wi.dot(h);
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        // Diffuse with retro-reflection at grazing angles, plus sheen
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d.powi(2);
+        let fd = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z()))
+            * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z()));
+        let diffuse = (fd / PI) * self.base_color;
+        let sheen_color = mix_color(white, self.tint(), self.sheen_tint);
+        let sheen = (self.sheen * schlick_weight(cos_d)) * sheen_color;
+
+        // Specular reflection, which is colored by the base color for metals
+        let specular_color = mix_color(white, self.tint(), self.specular_tint);
+        let f0 = mix_color(
+            (0.08 * self.specular) * specular_color,
+            self.base_color,
+            self.metallic,
+        );
+        let distribution = self.specular_distribution();
+        let specular = (distribution.d(h) * distribution.g(wo, wi) / (4.0 * wo.z() * wi.z()))
+            * schlick(f0, cos_d);
+
+        // Clearcoat, a second fixed-IOR specular lobe with a longer tail
+        let clearcoat_distribution = TrowbridgeReitz::new(0.25, 0.25);
+        let clearcoat = 0.25
+            * self.clearcoat
+            * gtr1(h.z(), self.clearcoat_alpha())
+            * clearcoat_distribution.g(wo, wi)
+            * mix(0.04, 1.0, schlick_weight(cos_d))
+            / (4.0 * wo.z() * wi.z());
+
+        let f = (1.0 - self.metallic) * (diffuse + sheen) + specular + clearcoat * white;
+
+        wi.z() * f
+    }
+
+    /// The combined probability density of sampling `wi` from any of the opaque lobes
+    fn pdf_opaque(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (wo + wi).normalized();
+        let (p_diffuse, p_specular, p_clearcoat) = self.lobe_probabilities();
+
+        let diffuse = wi.z() / PI;
+        let specular = self.specular_distribution().visible_normal_pdf(wo, h) / (4.0 * wo.dot(h));
+        let clearcoat = gtr1(h.z(), self.clearcoat_alpha()) * h.z() / (4.0 * wo.dot(h));
+
+        p_diffuse * diffuse + p_specular * specular + p_clearcoat * clearcoat
+    }
+
+    fn sample_opaque(&self, wo: Vec3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let (p_diffuse, p_specular, _) = self.lobe_probabilities();
+
+        let lobe: f64 = rng.gen();
+        if lobe < p_diffuse {
+            Vec3::random_cosine_direction()
+        } else if lobe < p_diffuse + p_specular {
+            let h = self.specular_distribution().sample_visible_normal(wo);
+            ((-1.0) * wo).reflect(h)
+        } else {
+            let h = sample_gtr1(self.clearcoat_alpha());
+            ((-1.0) * wo).reflect(h)
+        }
+    }
+}
+
+impl Scatter for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let mut rng = rand::thread_rng();
+
+        let transmission = self.transmission * (1.0 - self.metallic);
+        if rng.gen::<f64>() < transmission {
+            // Stochastically pick the glass part, only refracted light is tinted by the base color
+            let glass = RoughDielectric::new(self.index_of_refraction, self.roughness);
+            let (attenuation, scattered) = glass.scatter(r_in, rec)?;
+            if scattered.direction().dot(rec.normal) < 0.0 {
+                return Some((self.base_color * attenuation, scattered));
+            }
+            return Some((attenuation, scattered));
+        }
+
+        // Pick one of the opaque lobes, but weight by the density of all of them (one-sample MIS)
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let wi = self.sample_opaque(wo);
+
+        let pdf = self.pdf_opaque(wo, wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        let attenuation = (1.0 / pdf) * self.eval_opaque(wo, wi);
+        let scattered = Ray::with_wavelength(rec.point, frame.to_world(wi), r_in.wavelength());
+
+        Some((attenuation, scattered))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let transmission = self.transmission * (1.0 - self.metallic);
+
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let opaque = (1.0 - transmission) * self.eval_opaque(wo, frame.to_local(direction));
+        if transmission <= 0.0 {
+            return opaque;
+        }
+
+        let glass = RoughDielectric::new(self.index_of_refraction, self.roughness);
+        let mut glass_value = glass.eval(r_in, rec, direction);
+        if direction.dot(rec.normal) < 0.0 {
+            glass_value = self.base_color * glass_value;
+        }
+
+        opaque + transmission * glass_value
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let transmission = self.transmission * (1.0 - self.metallic);
+
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let opaque = (1.0 - transmission) * self.pdf_opaque(wo, frame.to_local(direction));
+        if transmission <= 0.0 {
+            return opaque;
+        }
+
+        let glass = RoughDielectric::new(self.index_of_refraction, self.roughness);
+        opaque + transmission * glass.pdf(r_in, rec, direction)
+    }
+}
+
+fn mix(a: f64, b: f64, t: f64) -> f64 {
+    (1.0 - t) * a + t * b
+}
+
+fn mix_color(a: Color, b: Color, t: f64) -> Color {
+    (1.0 - t) * a + t * b
+}
+
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+fn schlick(f0: Color, cos_theta: f64) -> Color {
+    mix_color(f0, Color::new(1.0, 1.0, 1.0), schlick_weight(cos_theta))
+}
+
+/// The generalized Trowbridge-Reitz distribution with `γ = 1` used by the clearcoat
+fn gtr1(cos_theta_h: f64, alpha: f64) -> f64 {
+    if cos_theta_h <= 0.0 {
+        return 0.0;
+    }
+
+    let alpha2 = alpha.powi(2);
+    (alpha2 - 1.0) / (PI * alpha2.ln() * (1.0 + (alpha2 - 1.0) * cos_theta_h.powi(2)))
+}
+
+/// Samples a half-vector proportional to `gtr1(cos_theta_h) * cos_theta_h`
+fn sample_gtr1(alpha: f64) -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+
+    let alpha2 = alpha.powi(2);
+    let cos_theta = ((1.0 - alpha2.powf(1.0 - r1)) / (1.0 - alpha2))
+        .max(0.0)
+        .sqrt();
+    let sin_theta = (1.0 - cos_theta.powi(2)).max(0.0).sqrt();
+    let phi = 2.0 * PI * r2;
+
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::hit::Hit;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+
+    fn materials() -> [Principled; 3] {
+        let color = Color::new(0.8, 0.4, 0.2);
+        [
+            Principled::new(color).with_roughness(0.6).with_sheen(0.5),
+            Principled::new(color)
+                .with_metallic(1.0)
+                .with_roughness(0.4),
+            Principled::new(color)
+                .with_metallic(0.5)
+                .with_clearcoat(1.0)
+                .with_clearcoat_gloss(0.3),
+        ]
+    }
+
+    /// A direction on the upper hemisphere, with a density of `1 / 2π`
+    fn uniform_direction() -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let z: f64 = rng.gen();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let r = (1.0 - z.powi(2)).sqrt();
+
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    #[test]
+    fn lobe_probabilities() {
+        for material in materials() {
+            let (diffuse, specular, clearcoat) = material.lobe_probabilities();
+            assert!((diffuse + specular + clearcoat - 1.0).abs() < 1.0e-12);
+        }
+
+        let metal = Principled::new(Color::new(1.0, 1.0, 1.0)).with_metallic(1.0);
+        assert_eq!(metal.lobe_probabilities(), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn pdf_integrates_to_at_most_one() {
+        let wo = Vec3::new(0.3, 0.0, 0.91_f64.sqrt());
+        for material in materials() {
+            let samples = 50000;
+            let integral = (0..samples)
+                .map(|_| material.pdf_opaque(wo, uniform_direction()) * 2.0 * PI)
+                .sum::<f64>()
+                / samples as f64;
+
+            // Reflections about the microfacets can end up below the surface
+            assert!(integral > 0.9 && integral < 1.02, "{}", integral);
+        }
+    }
+
+    #[test]
+    fn sampling_matches_pdf() {
+        // Importance sampling the lobes estimates the same reflectance as uniform sampling
+        let wo = Vec3::new(0.5, 0.2, 0.8).normalized();
+        for material in materials() {
+            let samples = 50000;
+            let mut sampled = Color::new(0.0, 0.0, 0.0);
+            let mut uniform = Color::new(0.0, 0.0, 0.0);
+            for _ in 0..samples {
+                let wi = material.sample_opaque(wo);
+                let pdf = material.pdf_opaque(wo, wi);
+                if pdf > 0.0 {
+                    sampled += (1.0 / pdf) * material.eval_opaque(wo, wi);
+                }
+
+                uniform += (2.0 * PI) * material.eval_opaque(wo, uniform_direction());
+            }
+
+            for channel in 0..3 {
+                let (sampled, uniform) = (sampled[channel], uniform[channel]);
+                assert!(
+                    (sampled - uniform).abs() < 0.03 * uniform,
+                    "{} isn't {}",
+                    sampled / samples as f64,
+                    uniform / samples as f64
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn weight_is_eval_over_pdf() {
+        let [plastic, metal, coated] = materials();
+        let glass = Principled::new(Color::new(0.8, 0.4, 0.2))
+            .with_roughness(0.3)
+            .with_transmission(1.0);
+
+        for material in [plastic, metal, coated, glass] {
+            let material = Arc::new(material);
+            let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material.clone());
+            let wo = Vec3::new(0.6, 0.0, 0.8);
+            let r_in = Ray::new(Point3::new(0.0, 0.0, 1.0) + 2.0 * wo, (-1.0) * wo);
+            let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+            for _ in 0..1000 {
+                let Some((attenuation, scattered)) = material.scatter(&r_in, &rec) else {
+                    continue;
+                };
+                let direction = scattered.direction().normalized();
+                let pdf = material.pdf(&r_in, &rec, direction);
+                assert!(pdf > 0.0);
+
+                let expected = (1.0 / pdf) * material.eval(&r_in, &rec, direction);
+                for channel in 0..3 {
+                    let tolerance = 1.0e-6 * expected[channel].max(1.0);
+                    assert!((attenuation[channel] - expected[channel]).abs() < tolerance);
+                }
+            }
+        }
+    }
+}
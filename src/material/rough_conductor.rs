@@ -0,0 +1,179 @@
+use crate::{color::Color, hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3};
+
+use super::{
+    microfacet::{fresnel_conductor, TrowbridgeReitz},
+    Scatter, Substrate,
+};
+
+/// A rough metal described by a GGX microfacet distribution and its complex index of refraction
+pub struct RoughConductor {
+    eta: Color,
+    k: Color,
+    distribution: TrowbridgeReitz,
+}
+
+impl RoughConductor {
+    pub fn new(eta: Color, k: Color, roughness: f64) -> RoughConductor {
+        RoughConductor {
+            eta,
+            k,
+            distribution: TrowbridgeReitz::from_roughness(roughness),
+        }
+    }
+
+    pub fn gold(roughness: f64) -> RoughConductor {
+        Self::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> RoughConductor {
+        Self::new(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> RoughConductor {
+        Self::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> RoughConductor {
+        Self::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+}
+
+impl Scatter for RoughConductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+
+        let m = self.distribution.sample_visible_normal(wo);
+        let wi = ((-1.0) * wo).reflect(m);
+        if wi.z() <= 0.0 {
+            return None;
+        }
+
+        // Sampling visible normals leaves only the Fresnel term and the shadowing part of G
+        let fresnel = fresnel_conductor(wo.dot(m), self.eta, self.k);
+        let shadowing = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+
+        let scattered = Ray::with_wavelength(rec.point, frame.to_world(wi), r_in.wavelength());
+
+        Some((shadowing * fresnel, scattered))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        let wi = frame.to_local(direction);
+
+        match self.distribution.eval_reflection(wo, wi) {
+            Some((h, value)) => value * fresnel_conductor(wo.dot(h), self.eta, self.k),
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+
+        self.distribution
+            .reflection_pdf(wo, frame.to_local(direction))
+    }
+
+    fn substrate(&self) -> Option<Substrate> {
+        Some(Substrate::Conductor {
+            eta: self.eta,
+            k: self.k,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::hit::Hit;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn conserves_energy() {
+        // A nearly perfect mirror only loses the light shadowed by the microfacets
+        let mirror = Arc::new(RoughConductor::new(
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(1.0e3, 1.0e3, 1.0e3),
+            0.5,
+        ));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, mirror.clone());
+        let wo = Vec3::new(0.5, 0.0, 0.75_f64.sqrt());
+        let r_in = Ray::new(Point3::new(0.0, 0.0, 1.0) + 2.0 * wo, (-1.0) * wo);
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        let samples = 10000;
+        let mut total = 0.0;
+        for _ in 0..samples {
+            if let Some((attenuation, scattered)) = mirror.scatter(&r_in, &rec) {
+                assert!(scattered.direction().dot(rec.normal) > 0.0);
+                assert!(attenuation[0] <= 1.0);
+                total += attenuation[0];
+            }
+        }
+
+        let albedo = total / samples as f64;
+        assert!(albedo > 0.85 && albedo <= 1.0, "{}", albedo);
+    }
+
+    #[test]
+    fn sampling_matches_eval_and_pdf() {
+        let gold = Arc::new(RoughConductor::new(
+            Color::new(0.2, 0.4, 1.4),
+            Color::new(3.6, 2.4, 1.8),
+            0.5,
+        ));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, gold.clone());
+        let wo = Vec3::new(0.5, 0.0, 0.75_f64.sqrt());
+        let r_in = Ray::new(Point3::new(0.0, 0.0, 1.0) + 2.0 * wo, (-1.0) * wo);
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        // The weight of each sample is the value over the density of its direction
+        for _ in 0..1000 {
+            let Some((attenuation, scattered)) = gold.scatter(&r_in, &rec) else {
+                continue;
+            };
+            let direction = scattered.direction().normalized();
+            let pdf = gold.pdf(&r_in, &rec, direction);
+            assert!(pdf > 0.0);
+
+            let expected = (1.0 / pdf) * gold.eval(&r_in, &rec, direction);
+            for channel in 0..3 {
+                assert!((attenuation[channel] - expected[channel]).abs() < 1.0e-6);
+            }
+        }
+
+        // Less the few reflections that would point into the surface
+        let samples = 100000;
+        let total: f64 = (0..samples)
+            .map(|_| gold.pdf(&r_in, &rec, Vec3::random_in_unit_sphere().normalized()))
+            .sum();
+        let integral = 4.0 * std::f64::consts::PI * total / samples as f64;
+        assert!(integral > 0.9 && integral < 1.02, "{}", integral);
+    }
+}
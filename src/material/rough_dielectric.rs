@@ -0,0 +1,197 @@
+use rand::Rng;
+
+use crate::{color::Color, hit::HitRecord, onb::Onb, ray::Ray, vec3::Vec3};
+
+use super::{
+    microfacet::{fresnel_dielectric, TrowbridgeReitz},
+    Scatter, Substrate,
+};
+
+/// Frosted glass described by a GGX microfacet distribution
+pub struct RoughDielectric {
+    index_of_refraction: f64,
+    distribution: TrowbridgeReitz,
+}
+
+impl RoughDielectric {
+    pub fn new(index_of_refraction: f64, roughness: f64) -> RoughDielectric {
+        RoughDielectric {
+            index_of_refraction,
+            distribution: TrowbridgeReitz::from_roughness(roughness),
+        }
+    }
+
+    /// Both directions in the local shading frame, and the ratio of the index of refraction
+    /// behind the surface over the one in front of it
+    fn local_directions(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> (Vec3, Vec3, f64) {
+        let eta = if rec.front_face {
+            self.index_of_refraction
+        } else {
+            1.0 / self.index_of_refraction
+        };
+
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+
+        (wo, frame.to_local(direction), eta)
+    }
+}
+
+impl Scatter for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let refraction_ratio = if rec.front_face {
+            1.0 / self.index_of_refraction
+        } else {
+            self.index_of_refraction
+        };
+
+        let frame = Onb::from_w(rec.normal);
+        let wo = frame.to_local((-1.0) * r_in.direction().normalized());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+
+        let m = self.distribution.sample_visible_normal(wo);
+        let reflectance = fresnel_dielectric(wo.dot(m), 1.0 / refraction_ratio);
+
+        // Choosing between reflection and refraction by the Fresnel term cancels it out
+        let mut rng = rand::thread_rng();
+        let wi = if rng.gen::<f64>() < reflectance {
+            let wi = ((-1.0) * wo).reflect(m);
+            if wi.z() <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            let wi = ((-1.0) * wo).refract(m, refraction_ratio);
+            if wi.z() >= 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        let shadowing = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+        let scattered = Ray::with_wavelength(rec.point, frame.to_world(wi), r_in.wavelength());
+
+        Some((Color::new(shadowing, shadowing, shadowing), scattered))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let (wo, wi, eta) = self.local_directions(r_in, rec, direction);
+
+        let value = if wi.z() > 0.0 {
+            self.distribution
+                .eval_reflection(wo, wi)
+                .map(|(h, value)| fresnel_dielectric(wo.dot(h), eta) * value)
+        } else {
+            self.distribution
+                .eval_transmission(wo, wi, eta)
+                .map(|(h, value)| (1.0 - fresnel_dielectric(wo.dot(h), eta)) * value)
+        };
+
+        let value = value.unwrap_or(0.0);
+        Color::new(value, value, value)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let (wo, wi, eta) = self.local_directions(r_in, rec, direction);
+
+        if wi.z() > 0.0 {
+            let h = (wo + wi).normalized();
+            fresnel_dielectric(wo.dot(h), eta) * self.distribution.reflection_pdf(wo, wi)
+        } else {
+            let h = (wo + eta * wi).normalized();
+            let reflectance = fresnel_dielectric(wo.dot(h).abs(), eta);
+            (1.0 - reflectance) * self.distribution.transmission_pdf(wo, wi, eta)
+        }
+    }
+
+    fn substrate(&self) -> Option<Substrate> {
+        Some(Substrate::Dielectric(self.index_of_refraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::hit::Hit;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn reflects_and_refracts() {
+        let glass = Arc::new(RoughDielectric::new(1.5, 0.3));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, glass.clone());
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let r_in = Ray::new(Point3::new(0.0, 0.0, 1.0) + 2.0 * wo, (-1.0) * wo);
+        let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+        let samples = 10000;
+        let (mut reflected, mut total) = (0, 0.0);
+        for _ in 0..samples {
+            if let Some((attenuation, scattered)) = glass.scatter(&r_in, &rec) {
+                assert!(attenuation[0] <= 1.0);
+                total += attenuation[0];
+                if scattered.direction().dot(rec.normal) > 0.0 {
+                    reflected += 1;
+                }
+            }
+        }
+
+        // Mostly refracted, about as often reflected as a smooth surface, and losing only the
+        // light shadowed by the microfacets
+        let reflected = reflected as f64 / samples as f64;
+        assert!(reflected > 0.02 && reflected < 0.1, "{}", reflected);
+        let albedo = total / samples as f64;
+        assert!(albedo > 0.9 && albedo <= 1.0, "{}", albedo);
+    }
+
+    #[test]
+    fn sampling_matches_eval_and_pdf() {
+        let glass = Arc::new(RoughDielectric::new(1.5, 0.8));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, glass.clone());
+
+        // From outside and from inside the glass
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let outside = Ray::new(Point3::new(0.0, 0.0, 1.0) + 2.0 * wo, (-1.0) * wo);
+        let inside = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.1, 0.0, 1.0));
+
+        for r_in in [outside, inside] {
+            let rec = sphere.hit(&r_in, 0.001, f64::INFINITY).unwrap();
+
+            // The weight of each sample is the value over the density of its direction
+            let samples = 20000;
+            let mut sampled = 0;
+            for _ in 0..samples {
+                let Some((attenuation, scattered)) = glass.scatter(&r_in, &rec) else {
+                    continue;
+                };
+                sampled += 1;
+                let direction = scattered.direction().normalized();
+                let pdf = glass.pdf(&r_in, &rec, direction);
+                assert!(pdf > 0.0);
+
+                let expected = (1.0 / pdf) * glass.eval(&r_in, &rec, direction);
+                assert!((attenuation[0] - expected[0]).abs() < 1.0e-6 * expected[0].max(1.0));
+            }
+
+            // The density integrates to the probability of a sample ending up on the right side
+            // of the surface
+            let samples = 200000;
+            let total: f64 = (0..samples)
+                .map(|_| glass.pdf(&r_in, &rec, Vec3::random_in_unit_sphere().normalized()))
+                .sum();
+            let integral = 4.0 * std::f64::consts::PI * total / samples as f64;
+            let sampled = sampled as f64 / 20000.0;
+            assert!(
+                (integral - sampled).abs() < 0.05,
+                "{} {}",
+                integral,
+                sampled
+            );
+        }
+    }
+}
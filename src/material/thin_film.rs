@@ -0,0 +1,300 @@
+use std::{
+    f64::consts::PI,
+    ops::{Add, Div, Mul, Sub},
+    sync::Arc,
+};
+
+use crate::{color::Color, hit::HitRecord, ray::Ray, texture::Texture, vec3::Vec3};
+
+use super::Scatter;
+
+/// Wavelengths (in nanometers) representing the red, green and blue channel for rays that don't
+/// carry a wavelength of their own
+const RGB_WAVELENGTHS: [f64; 3] = [650.0, 532.0, 450.0];
+
+/// What the film is deposited on, which determines the reflection at its lower interface
+#[derive(Clone, Copy)]
+pub enum Substrate {
+    Dielectric(f64),
+    /// A conductor with the complex index of refraction `eta + i k` per color channel
+    Conductor {
+        eta: Color,
+        k: Color,
+    },
+}
+
+/// A thin transparent film on top of another material, such as oil on water or the oxide layer
+/// on heated metal, whose interference tints the reflections of the material beneath.
+///
+/// Only bases with a smooth interface, like dielectrics and conductors, are tinted. The film has
+/// no effect on diffuse bases, whose scattering isn't governed by the reflectance the film
+/// changes.
+pub struct ThinFilm {
+    base: Arc<dyn Scatter>,
+    /// The optical properties of the base, if it has a smooth interface
+    substrate: Option<Substrate>,
+    /// The thickness of the film in nanometers
+    thickness: f64,
+    film_ior: f64,
+    thickness_variation: Option<Arc<dyn Texture>>,
+}
+
+impl ThinFilm {
+    pub fn new(base: Arc<dyn Scatter>, thickness: f64, film_ior: f64) -> ThinFilm {
+        ThinFilm {
+            substrate: base.substrate(),
+            base,
+            thickness,
+            film_ior,
+            thickness_variation: None,
+        }
+    }
+
+    /// Scales the thickness by the luminance of the texture at each point
+    pub fn with_thickness_variation(self, variation: Arc<dyn Texture>) -> ThinFilm {
+        ThinFilm {
+            thickness_variation: Some(variation),
+            ..self
+        }
+    }
+
+    /// Reflectance of the air-film-substrate stack, and of the bare substrate without the film
+    fn reflectance(
+        substrate: Substrate,
+        film_ior: f64,
+        cos_theta: f64,
+        thickness: f64,
+        wavelength: f64,
+        channel: usize,
+    ) -> (f64, f64) {
+        let n3 = match substrate {
+            Substrate::Dielectric(ior) => Complex::new(ior, 0.0),
+            Substrate::Conductor { eta, k } => Complex::new(eta[channel], k[channel]),
+        };
+        let n1 = Complex::new(1.0, 0.0);
+        let n2 = Complex::new(film_ior, 0.0);
+
+        // Snell's law, in complex form for absorbing substrates
+        let sin2_theta = 1.0 - cos_theta.powi(2);
+        let cos_in = |n: Complex| (Complex::new(1.0, 0.0) - (n * n).inverse() * sin2_theta).sqrt();
+        let c1 = Complex::new(cos_theta, 0.0);
+        let c2 = cos_in(n2);
+        let c3 = cos_in(n3);
+
+        // Phase difference between successive reflections inside the film
+        let delta = (4.0 * PI * thickness / wavelength) * (n2 * c2);
+        let phase = Complex::exp_i(delta.re) * (-delta.im).exp();
+
+        let airy = |r12: Complex, r23: Complex, r13: Complex| {
+            let film = (r12 + r23 * phase) / (Complex::new(1.0, 0.0) + r12 * r23 * phase);
+            (film.norm_sqr(), r13.norm_sqr())
+        };
+
+        let s_polarized = |na: Complex, ca: Complex, nb: Complex, cb: Complex| {
+            (na * ca - nb * cb) / (na * ca + nb * cb)
+        };
+        let p_polarized = |na: Complex, ca: Complex, nb: Complex, cb: Complex| {
+            (nb * ca - na * cb) / (nb * ca + na * cb)
+        };
+
+        let (film_s, bare_s) = airy(
+            s_polarized(n1, c1, n2, c2),
+            s_polarized(n2, c2, n3, c3),
+            s_polarized(n1, c1, n3, c3),
+        );
+        let (film_p, bare_p) = airy(
+            p_polarized(n1, c1, n2, c2),
+            p_polarized(n2, c2, n3, c3),
+            p_polarized(n1, c1, n3, c3),
+        );
+
+        (0.5 * (film_s + film_p), 0.5 * (bare_s + bare_p))
+    }
+
+    /// How much the film changes the reflectance of the surface, or its transmittance if the
+    /// light isn't `reflected`, by which the base material's scattering is reweighted
+    fn tint(&self, r_in: &Ray, rec: &HitRecord, reflected: bool) -> Color {
+        let Some(substrate) = self.substrate else {
+            return Color::new(1.0, 1.0, 1.0);
+        };
+
+        let cos_theta = ((-1.0) * r_in.direction().normalized())
+            .dot(rec.normal)
+            .clamp(0.0, 1.0);
+        let thickness = match &self.thickness_variation {
+            Some(variation) => {
+                self.thickness * variation.value(rec.u, rec.v, rec.point).luminance()
+            }
+            None => self.thickness,
+        };
+
+        let mut tint = Color::new(1.0, 1.0, 1.0);
+        for channel in 0..3 {
+            let wavelength = r_in.wavelength().unwrap_or(RGB_WAVELENGTHS[channel]);
+            let (film, bare) = Self::reflectance(
+                substrate,
+                self.film_ior,
+                cos_theta,
+                thickness,
+                wavelength,
+                channel,
+            );
+
+            tint[channel] = if reflected {
+                film / bare.max(1.0e-4)
+            } else {
+                (1.0 - film) / (1.0 - bare).max(1.0e-4)
+            };
+        }
+
+        tint
+    }
+}
+
+impl Scatter for ThinFilm {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let (attenuation, scattered) = self.base.scatter(r_in, rec)?;
+        if !rec.front_face {
+            // The film only coats the outside of the surface
+            return Some((attenuation, scattered));
+        }
+
+        let reflected = scattered.direction().dot(rec.normal) > 0.0;
+        Some((self.tint(r_in, rec, reflected) * attenuation, scattered))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let value = self.base.eval(r_in, rec, direction);
+        if !rec.front_face || value.is_black() {
+            return value;
+        }
+
+        let reflected = direction.dot(rec.normal) > 0.0;
+        self.tint(r_in, rec, reflected) * value
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        // The film only reweights what the base material samples
+        self.base.pdf(r_in, rec, direction)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// `e^(i phi)`
+    fn exp_i(phi: f64) -> Complex {
+        Complex::new(phi.cos(), phi.sin())
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.re.powi(2) + self.im.powi(2)
+    }
+
+    fn inverse(self) -> Complex {
+        let norm_sqr = self.norm_sqr();
+        Complex::new(self.re / norm_sqr, -self.im / norm_sqr)
+    }
+
+    /// The principal square root
+    fn sqrt(self) -> Complex {
+        let r = self.norm_sqr().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: f64) -> Complex {
+        Complex::new(self.re * other, self.im * other)
+    }
+}
+
+impl Mul<Complex> for f64 {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self * other.re, self * other.im)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let norm_sqr = other.norm_sqr();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / norm_sqr,
+            (self.im * other.re - self.re * other.im) / norm_sqr,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanishing_film_reflects_like_the_substrate() {
+        let substrates = [
+            Substrate::Dielectric(1.5),
+            Substrate::Conductor {
+                eta: Color::new(0.2, 0.9, 1.1),
+                k: Color::new(3.9, 2.4, 2.2),
+            },
+        ];
+
+        for substrate in substrates {
+            for cos_theta in [1.0, 0.7, 0.3, 0.05] {
+                for channel in 0..3 {
+                    let (film, bare) =
+                        ThinFilm::reflectance(substrate, 1.33, cos_theta, 0.0, 550.0, channel);
+                    assert!((film - bare).abs() < 1.0e-9, "{} != {}", film, bare);
+                }
+            }
+        }
+
+        // At normal incidence the bare dielectric reflects ((n - 1) / (n + 1))^2
+        let (_, bare) = ThinFilm::reflectance(substrates[0], 1.33, 1.0, 0.0, 550.0, 0);
+        assert!((bare - 0.04).abs() < 1.0e-9);
+    }
+}
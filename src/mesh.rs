@@ -0,0 +1,473 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::Rng;
+
+use crate::hit::{Hit, HitRecord};
+use crate::material::Scatter;
+use crate::point3::Point3;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Barycentric coordinates of the points at which the emission of each triangle is estimated
+const EMISSION_SAMPLES: [(f64, f64); 4] = [
+    (1.0 / 3.0, 1.0 / 3.0),
+    (1.0 / 6.0, 1.0 / 6.0),
+    (2.0 / 3.0, 1.0 / 6.0),
+    (1.0 / 6.0, 2.0 / 3.0),
+];
+
+/// A flat shaded triangle mesh, whose triangles face the side from which their vertices appear in
+/// counter-clockwise order.
+///
+/// If its material emits light, the mesh acts as an area light whose triangles are sampled
+/// proportionally to an estimate of the power they emit.
+pub struct Mesh {
+    positions: Vec<Point3>,
+    /// Texture coordinates for each vertex, or empty to use the barycentric coordinates instead
+    uvs: Vec<(f64, f64)>,
+    triangles: Vec<[usize; 3]>,
+    material: Arc<dyn Scatter>,
+    min: Point3,
+    max: Point3,
+    /// Cumulative distribution of picking each triangle when sampling the mesh as a light
+    cdf: Vec<f64>,
+    power: f64,
+}
+
+impl Mesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        uvs: Vec<(f64, f64)>,
+        triangles: Vec<[usize; 3]>,
+        material: Arc<dyn Scatter>,
+    ) -> Mesh {
+        assert!(
+            uvs.is_empty() || uvs.len() == positions.len(),
+            "Texture coordinates don't match the vertices"
+        );
+        assert!(
+            triangles.iter().flatten().all(|&i| i < positions.len()),
+            "Triangle refers to a missing vertex"
+        );
+
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for p in &positions {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+
+        let mut mesh = Mesh {
+            positions,
+            uvs,
+            triangles,
+            material,
+            min,
+            max,
+            cdf: Vec::new(),
+            power: 0.0,
+        };
+        mesh.build_emission_distribution();
+
+        mesh
+    }
+
+    /// Estimates the power of each triangle from its emission seen from straight in front, and
+    /// builds the distribution for sampling them
+    fn build_emission_distribution(&mut self) {
+        let mut powers = Vec::with_capacity(self.triangles.len());
+        let mut areas = Vec::with_capacity(self.triangles.len());
+
+        for index in 0..self.triangles.len() {
+            let (p0, e1, e2) = self.edges(index);
+            let cross = e1.cross(e2);
+            let area = 0.5 * cross.length();
+            areas.push(area);
+            if area <= 0.0 {
+                powers.push(0.0);
+                continue;
+            }
+
+            let normal = cross.normalized();
+            let luminance: f64 = EMISSION_SAMPLES
+                .iter()
+                .map(|&(b1, b2)| {
+                    let point = p0 + b1 * e1 + b2 * e2;
+                    let ray = Ray::new(point + normal, (-1.0) * normal);
+                    let rec = self.record(index, &ray, 1.0, (b1, b2));
+                    rec.material.emitted(&rec).luminance()
+                })
+                .sum::<f64>()
+                / (EMISSION_SAMPLES.len() as f64);
+
+            powers.push(PI * area * luminance);
+        }
+
+        self.power = powers.iter().sum();
+        if self.power <= 0.0 {
+            return;
+        }
+
+        // Mostly by power, but keep every triangle reachable in case the estimate missed some of
+        // its emission
+        let total_area: f64 = areas.iter().sum();
+        let mut cumulative = 0.0;
+        self.cdf = powers
+            .iter()
+            .zip(&areas)
+            .map(|(power, area)| {
+                cumulative += 0.9 * power / self.power + 0.1 * area / total_area;
+                cumulative
+            })
+            .collect();
+    }
+
+    /// The first vertex of a triangle and its two edges starting there
+    fn edges(&self, index: usize) -> (Point3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.triangles[index];
+        let p0 = self.positions[i0];
+
+        (p0, self.positions[i1] - p0, self.positions[i2] - p0)
+    }
+
+    /// The probability of picking a triangle when sampling the mesh as a light
+    fn triangle_probability(&self, index: usize) -> f64 {
+        let previous = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        self.cdf[index] - previous
+    }
+
+    /// The record of `ray` hitting a triangle at parameter `t`, where the barycentric
+    /// coordinates `b1` and `b2` are the weights of its second and third vertex
+    fn record(&self, index: usize, ray: &Ray, t: f64, (b1, b2): (f64, f64)) -> HitRecord {
+        let [i0, i1, i2] = self.triangles[index];
+        let (_, e1, e2) = self.edges(index);
+        let outward_normal = e1.cross(e2).normalized();
+
+        let (uv, derivatives) = if self.uvs.is_empty() {
+            ((b1, b2), (e1, e2))
+        } else {
+            let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+            let b0 = 1.0 - b1 - b2;
+            let uv = (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            );
+
+            // How the point changes with the texture coordinates follows from their change along
+            // the edges
+            let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+            let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+            let det = du1 * dv2 - du2 * dv1;
+            let derivatives = if det.abs() > 1.0e-12 {
+                (
+                    (1.0 / det) * (dv2 * e1 - dv1 * e2),
+                    (1.0 / det) * (du1 * e2 - du2 * e1),
+                )
+            } else {
+                (e1, e2)
+            };
+
+            (uv, derivatives)
+        };
+
+        HitRecord::new(
+            ray.at(t),
+            self.material.clone(),
+            t,
+            uv,
+            ray,
+            outward_normal,
+            derivatives,
+        )
+    }
+
+    /// Intersects a single triangle (Möller-Trumbore), returning the ray parameter and the
+    /// barycentric coordinates of the hit
+    fn intersect(
+        &self,
+        index: usize,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let (p0, e1, e2) = self.edges(index);
+
+        let p = ray.direction().cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < 1.0e-12 {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = ray.origin() - p0;
+        let b1 = inv_det * s.dot(p);
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(e1);
+        let b2 = inv_det * ray.direction().dot(q);
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = inv_det * e2.dot(q);
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        Some((t, b1, b2))
+    }
+
+    /// The nearest triangle hit by the ray, with the ray parameter and barycentric coordinates
+    fn hit_triangle(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(usize, f64, f64, f64)> {
+        // Skip the triangles if the ray misses the bounding box (slab method)
+        let mut t_enter = t_min;
+        let mut t_exit = t_max;
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction()[axis];
+            let mut t0 = (self.min[axis] - ray.origin()[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_enter = t_enter.max(t0);
+            t_exit = t_exit.min(t1);
+            if t_exit < t_enter {
+                return None;
+            }
+        }
+
+        let mut closest = None;
+        let mut t_closest = t_max;
+        for index in 0..self.triangles.len() {
+            if let Some((t, b1, b2)) = self.intersect(index, ray, t_min, t_closest) {
+                t_closest = t;
+                closest = Some((index, t, b1, b2));
+            }
+        }
+
+        closest
+    }
+
+    /// Converts the probability of sampling a point on a triangle by area to a density with
+    /// respect to solid angle as seen from `origin`
+    fn solid_angle_pdf(&self, index: usize, origin: Point3, rec: &HitRecord) -> f64 {
+        let (_, e1, e2) = self.edges(index);
+        let area = 0.5 * e1.cross(e2).length();
+
+        let to_point = rec.point - origin;
+        let distance2 = to_point.length().powi(2);
+        let cos_theta = rec.normal.dot(to_point / distance2.sqrt()).abs();
+        if area <= 0.0 || cos_theta <= 1.0e-6 {
+            return 0.0;
+        }
+
+        self.triangle_probability(index) / area * distance2 / cos_theta
+    }
+}
+
+impl Hit for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (index, t, b1, b2) = self.hit_triangle(ray, t_min, t_max)?;
+        Some(self.record(index, ray, t, (b1, b2)))
+    }
+
+    fn power(&self) -> f64 {
+        self.power
+    }
+
+    fn bounding_box(&self) -> Option<(Point3, Point3)> {
+        Some((self.min, self.max))
+    }
+
+    fn normal_cone(&self) -> (Vec3, f64) {
+        let normals: Vec<Vec3> = (0..self.triangles.len())
+            .map(|index| {
+                let (_, e1, e2) = self.edges(index);
+                e1.cross(e2)
+            })
+            .filter(|normal| !normal.near_zero())
+            .collect();
+
+        // The area weighted average normal, and the widest angle to any of the normals
+        let sum = normals
+            .iter()
+            .fold(Vec3::new(0.0, 0.0, 0.0), |sum, &normal| sum + normal);
+        if sum.near_zero() {
+            return (Vec3::new(0.0, 0.0, 1.0), -1.0);
+        }
+
+        let axis = sum.normalized();
+        let cos_theta = normals
+            .iter()
+            .map(|normal| axis.dot(normal.normalized()))
+            .fold(1.0, f64::min);
+
+        (axis, cos_theta)
+    }
+
+    fn sample_point(&self, origin: Point3) -> Option<(HitRecord, f64)> {
+        let mut rng = rand::thread_rng();
+        let total = *self.cdf.last()?;
+
+        let xi = rng.gen::<f64>() * total;
+        let index = self
+            .cdf
+            .partition_point(|&c| c < xi)
+            .min(self.cdf.len() - 1);
+
+        // Uniformly distributed barycentric coordinates
+        let su = rng.gen::<f64>().sqrt();
+        let b1 = 1.0 - su;
+        let b2 = rng.gen::<f64>() * su;
+
+        let (p0, e1, e2) = self.edges(index);
+        let point = p0 + b1 * e1 + b2 * e2;
+        let rec = self.record(index, &Ray::new(origin, point - origin), 1.0, (b1, b2));
+
+        let pdf = self.solid_angle_pdf(index, origin, &rec);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some((rec, pdf))
+    }
+
+    fn point_pdf(&self, origin: Point3, rec: &HitRecord) -> f64 {
+        if self.cdf.is_empty() {
+            return 0.0;
+        }
+
+        // Find the triangle again, the point is the first one seen from the origin
+        let ray = Ray::new(origin, rec.point - origin);
+        match self.hit_triangle(&ray, 1.0e-6, 1.0 + 1.0e-6) {
+            Some((index, _, _, _)) => self.solid_angle_pdf(index, origin, rec),
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::DiffuseLight;
+    use crate::texture::Texture;
+
+    /// Emits only on the side of the positive x-axis
+    struct HalfLit;
+
+    impl Texture for HalfLit {
+        fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+            if p.x() > 0.0 {
+                Color::new(1.0, 1.0, 1.0)
+            } else {
+                Color::new(0.0, 0.0, 0.0)
+            }
+        }
+    }
+
+    /// A dark triangle of area 2 and a lit one of area 0.5 next to it, both facing up the z-axis
+    fn half_lit_mesh() -> Mesh {
+        let positions = vec![
+            Point3::new(-3.0, -1.0, 0.0),
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(-1.0, 1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(2.0, -1.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        ];
+        let material = Arc::new(DiffuseLight::textured(Arc::new(HalfLit)));
+
+        Mesh::new(positions, Vec::new(), vec![[0, 1, 2], [3, 4, 5]], material)
+    }
+
+    #[test]
+    fn triangles_are_picked_by_power_and_area() {
+        let mesh = half_lit_mesh();
+        assert!((mesh.power - PI * 0.5).abs() < 1.0e-12);
+
+        // Nine tenths by power, all to the lit triangle, and a tenth by area
+        let expected = [0.1 * 2.0 / 2.5, 0.9 + 0.1 * 0.5 / 2.5];
+        for (index, expected) in expected.into_iter().enumerate() {
+            assert!((mesh.triangle_probability(index) - expected).abs() < 1.0e-12);
+        }
+
+        let origin = Point3::new(0.0, 0.0, 2.0);
+        let samples = 100_000;
+        let mut dark = 0;
+        for _ in 0..samples {
+            let (rec, pdf) = mesh.sample_point(origin).unwrap();
+            assert!((pdf - mesh.point_pdf(origin, &rec)).abs() < 1.0e-9 * pdf);
+            if rec.point.x() < 0.0 {
+                dark += 1;
+            }
+        }
+        let frequency = dark as f64 / samples as f64;
+        assert!((frequency - expected[0]).abs() < 0.005, "{}", frequency);
+    }
+
+    #[test]
+    fn point_pdf_integrates_to_one() {
+        let mesh = half_lit_mesh();
+        let origin = Point3::new(0.5, -0.5, 2.0);
+        let mut rng = rand::thread_rng();
+
+        // Convert the density over solid angle back to one over the area, and integrate that over
+        // each triangle
+        let samples = 1000;
+        let mut integral = 0.0;
+        for index in 0..mesh.triangles.len() {
+            let (p0, e1, e2) = mesh.edges(index);
+            let area = 0.5 * e1.cross(e2).length();
+            for _ in 0..samples {
+                let su = rng.gen::<f64>().sqrt();
+                let point = p0 + (1.0 - su) * e1 + (rng.gen::<f64>() * su) * e2;
+                let to_point = point - origin;
+                let rec = mesh.hit(&Ray::new(origin, to_point), 0.001, 2.0).unwrap();
+
+                let cos_theta = rec.normal.dot(to_point.normalized()).abs();
+                let pdf = mesh.point_pdf(origin, &rec) * cos_theta / to_point.length().powi(2);
+                integral += pdf * area / samples as f64;
+            }
+        }
+
+        assert!((integral - 1.0).abs() < 1.0e-6, "{}", integral);
+    }
+
+    #[test]
+    fn dark_mesh_is_no_light() {
+        let mesh = Mesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            Vec::new(),
+            vec![[0, 1, 2]],
+            Arc::new(DiffuseLight::new(Color::new(0.0, 0.0, 0.0))),
+        );
+
+        assert_eq!(mesh.power(), 0.0);
+        assert!(mesh.sample_point(Point3::new(0.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn rays_missing_the_bounds_miss_the_mesh() {
+        let mesh = half_lit_mesh();
+        let down = Vec3::new(0.0, 0.0, -1.0);
+
+        let rec = mesh.hit(&Ray::new(Point3::new(-1.5, 0.0, 1.0), down), 0.001, 10.0);
+        assert!((rec.unwrap().t - 1.0).abs() < 1.0e-12);
+        assert!(mesh
+            .hit(&Ray::new(Point3::new(0.0, 0.0, 1.0), down), 0.001, 10.0)
+            .is_none());
+        assert!(mesh
+            .hit(&Ray::new(Point3::new(-1.5, 3.0, 1.0), down), 0.001, 10.0)
+            .is_none());
+    }
+}
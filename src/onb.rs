@@ -0,0 +1,36 @@
+use crate::vec3::Vec3;
+
+/// An orthonormal basis, used to move directions in and out of a surface's local shading frame
+/// where the normal is the z-axis
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn new(u: Vec3, v: Vec3, w: Vec3) -> Onb {
+        Onb { u, v, w }
+    }
+
+    pub fn from_w(w: Vec3) -> Onb {
+        let w = w.normalized();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).normalized();
+        let u = v.cross(w);
+
+        Onb { u, v, w }
+    }
+
+    pub fn to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(a.dot(self.u), a.dot(self.v), a.dot(self.w))
+    }
+
+    pub fn to_world(&self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
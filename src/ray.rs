@@ -4,11 +4,26 @@ use crate::vec3::Vec3;
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+    /// The wavelength (in nanometers) this ray carries, if it has been narrowed
+    /// down to a single wavelength by a dispersive material
+    wavelength: Option<f64>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            wavelength: None,
+        }
+    }
+
+    pub fn with_wavelength(origin: Point3, direction: Vec3, wavelength: Option<f64>) -> Ray {
+        Ray {
+            origin,
+            direction,
+            wavelength,
+        }
     }
 
     pub fn origin(&self) -> Point3 {
@@ -19,6 +34,10 @@ impl Ray {
         self.direction
     }
 
+    pub fn wavelength(&self) -> Option<f64> {
+        self.wavelength
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + t * self.direction
     }
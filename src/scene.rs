@@ -0,0 +1,845 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::Rng;
+
+use crate::alpha_mask::AlphaMask;
+use crate::color::Color;
+use crate::density_grid::DensityGrid;
+use crate::environment::{EnvironmentMap, Gradient, Sky};
+use crate::heterogeneous_medium::HeterogeneousMedium;
+use crate::hit::World;
+use crate::light::{DirectionalLight, GoniometricLight, IesProfile, PointLight, SpotLight};
+use crate::material::{
+    BumpMap, Dielectric, DiffuseLight, Dispersion, DispersiveDielectric, HenyeyGreenstein,
+    Lambertian, Layered, Metal, MixMaterial, NormalMap, OrenNayar, Principled, RoughConductor,
+    RoughDielectric, Scatter, ThinFilm,
+};
+use crate::mesh::Mesh;
+use crate::point3::Point3;
+use crate::sphere::Sphere;
+use crate::subsurface::Subsurface;
+use crate::texture::{Checker, ImageTexture, Noise, SolidColor};
+use crate::vec3::Vec3;
+
+pub fn random() -> World {
+    let mut rng = rand::thread_rng();
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    for a in -11..=11 {
+        for b in -11..=11 {
+            let choose_mat: f64 = rng.gen();
+            let center = Point3::new(
+                (a as f64) + rng.gen_range(0.0..0.9),
+                0.2,
+                (b as f64) + rng.gen_range(0.0..0.9),
+            );
+
+            if choose_mat < 0.8 {
+                // Diffuse
+                let albedo = Color::random(0.0..1.0) * Color::random(0.0..1.0);
+                let sphere_mat = Arc::new(Lambertian::new(albedo));
+                let sphere = Sphere::new(center, 0.2, sphere_mat);
+
+                world.push(Box::new(sphere));
+            } else if choose_mat < 0.95 {
+                // Metal
+                let albedo = Color::random(0.4..1.0);
+                let fuzz = rng.gen_range(0.0..0.5);
+                let sphere_mat = Arc::new(Metal::new(albedo, fuzz));
+                let sphere = Sphere::new(center, 0.2, sphere_mat);
+
+                world.push(Box::new(sphere));
+            } else {
+                // Glass
+                let sphere_mat = Arc::new(Dielectric::new(1.5));
+                let sphere = Sphere::new(center, 0.2, sphere_mat);
+
+                world.push(Box::new(sphere));
+            }
+        }
+    }
+
+    let mat1 = Arc::new(Dielectric::new(1.5));
+    let mat2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    let mat3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+
+    let sphere1 = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1);
+    let sphere2 = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2);
+    let sphere3 = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3);
+
+    world.push(Box::new(sphere1));
+    world.push(Box::new(sphere2));
+    world.push(Box::new(sphere3));
+
+    world
+}
+
+pub fn dispersion() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // Strongly exaggerated dispersion to make the rainbow fringes obvious
+    let mat1 = Arc::new(DispersiveDielectric::new(Dispersion::Cauchy {
+        a: 1.5,
+        b: 0.05,
+    }));
+    let mat2 = Arc::new(DispersiveDielectric::new(Dispersion::DIAMOND));
+    let mat3 = Arc::new(DispersiveDielectric::new(Dispersion::BK7));
+    let mat4 = Arc::new(DispersiveDielectric::new(Dispersion::FUSED_SILICA));
+
+    let sphere1 = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1);
+    let sphere2 = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2);
+    let sphere3 = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3);
+    let sphere4 = Sphere::new(Point3::new(2.0, 0.5, 2.0), 0.5, mat4);
+
+    world.push(Box::new(sphere1));
+    world.push(Box::new(sphere2));
+    world.push(Box::new(sphere3));
+    world.push(Box::new(sphere4));
+
+    world
+}
+
+pub fn colored_glass() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // The same glass in different thicknesses, the larger spheres appear darker
+    let absorption = Color::new(0.1, 0.6, 0.9);
+    for (i, radius) in [0.25, 0.5, 1.0, 1.5].into_iter().enumerate() {
+        let mat = Arc::new(Dielectric::new(1.5).with_absorption(absorption));
+        let center = Point3::new(3.0 - 2.5 * (i as f64), radius, 0.0);
+        world.push(Box::new(Sphere::new(center, radius, mat)));
+    }
+
+    let amber_mat = Arc::new(
+        DispersiveDielectric::new(Dispersion::BK7).with_absorption(Color::new(0.2, 0.5, 1.5)),
+    );
+    let amber_sphere = Sphere::new(Point3::new(1.5, 0.4, 2.0), 0.4, amber_mat);
+
+    world.push(Box::new(amber_sphere));
+
+    world
+}
+
+pub fn microfacet() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // Front row: conductors of increasing roughness, back row: glass of increasing roughness
+    for (i, roughness) in [0.05, 0.2, 0.4, 0.7].into_iter().enumerate() {
+        let x = 3.0 - 2.0 * (i as f64);
+
+        let conductor_mat = Arc::new(match i {
+            0 => RoughConductor::gold(roughness),
+            1 => RoughConductor::copper(roughness),
+            2 => RoughConductor::silver(roughness),
+            _ => RoughConductor::aluminium(roughness),
+        });
+        let conductor_sphere = Sphere::new(Point3::new(x, 0.6, 1.2), 0.6, conductor_mat);
+
+        let dielectric_mat = Arc::new(RoughDielectric::new(1.5, roughness));
+        let dielectric_sphere = Sphere::new(Point3::new(x, 0.6, -1.2), 0.6, dielectric_mat);
+
+        world.push(Box::new(conductor_sphere));
+        world.push(Box::new(dielectric_sphere));
+    }
+
+    world
+}
+
+pub fn principled() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Principled::new(Color::new(0.5, 0.5, 0.5)).with_roughness(1.0));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let red = Color::new(0.8, 0.1, 0.1);
+    let materials = [
+        // Glossy plastic
+        Principled::new(red).with_roughness(0.2).with_specular(0.8),
+        // Rough gold
+        Principled::new(Color::new(1.0, 0.78, 0.34))
+            .with_metallic(1.0)
+            .with_roughness(0.35),
+        // Car paint
+        Principled::new(red)
+            .with_metallic(0.5)
+            .with_specular_tint(0.5)
+            .with_clearcoat(1.0)
+            .with_clearcoat_gloss(0.9),
+        // Velvet
+        Principled::new(Color::new(0.3, 0.05, 0.4))
+            .with_roughness(1.0)
+            .with_specular(0.0)
+            .with_sheen(1.0)
+            .with_sheen_tint(0.8),
+        // Tinted frosted glass
+        Principled::new(Color::new(0.7, 0.9, 0.8))
+            .with_roughness(0.1)
+            .with_transmission(1.0)
+            .with_index_of_refraction(1.45),
+    ];
+
+    for (i, mat) in materials.into_iter().enumerate() {
+        let center = Point3::new(4.0 - 2.0 * (i as f64), 0.8, 0.0);
+        world.push(Box::new(Sphere::new(center, 0.8, Arc::new(mat))));
+    }
+
+    world
+}
+
+pub fn anisotropic() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // Brushed steel, stretched along the longitude, along the latitude and in between
+    let albedo = Color::new(0.8, 0.8, 0.85);
+    let mat1 = Arc::new(Metal::anisotropic(albedo, 0.4, 0.05, 0.0));
+    let mat2 = Arc::new(Metal::anisotropic(albedo, 0.4, 0.05, 90.0));
+    let mat3 = Arc::new(Metal::anisotropic(albedo, 0.4, 0.05, 45.0));
+
+    let sphere1 = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1);
+    let sphere2 = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2);
+    let sphere3 = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3);
+
+    world.push(Box::new(sphere1));
+    world.push(Box::new(sphere2));
+    world.push(Box::new(sphere3));
+
+    world
+}
+
+pub fn oren_nayar() -> World {
+    let mut world = World::new();
+
+    let ground_tex = Arc::new(Checker::new(
+        1.0,
+        Arc::new(SolidColor::new(Color::new(0.2, 0.3, 0.1))),
+        Arc::new(SolidColor::new(Color::new(0.9, 0.9, 0.9))),
+    ));
+    let ground_mat = Arc::new(OrenNayar::textured(ground_tex, 30.0));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // Clay of increasing roughness, the left one matches a Lambertian surface
+    let clay = Color::new(0.75, 0.45, 0.3);
+    for (i, sigma) in [0.0, 20.0, 40.0, 60.0].into_iter().enumerate() {
+        let mat = Arc::new(OrenNayar::new(clay, sigma));
+        let center = Point3::new(3.0 - 2.0 * (i as f64), 0.8, 0.0);
+        world.push(Box::new(Sphere::new(center, 0.8, mat)));
+    }
+
+    world
+}
+
+pub fn thin_film() -> World {
+    let mut world = World::new();
+
+    // A puddle of water covered by a swirly film of oil
+    let water = Arc::new(Dielectric::new(1.33));
+    let oil_mat = Arc::new(
+        ThinFilm::new(water, 600.0, 1.47).with_thickness_variation(Arc::new(Noise::new(0.5))),
+    );
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, oil_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let bottom_mat = Arc::new(Lambertian::new(Color::new(0.1, 0.1, 0.1)));
+    let bottom_sphere = Sphere::new(Point3::new(0.0, -1000.5, 0.0), 1000.0, bottom_mat);
+
+    world.push(Box::new(bottom_sphere));
+
+    // Heat-tinted titanium, the oxide layer getting thicker from left to right
+    for (i, thickness) in [50.0, 150.0, 250.0].into_iter().enumerate() {
+        let titanium = Arc::new(RoughConductor::new(
+            Color::new(2.74, 2.54, 2.25),
+            Color::new(3.82, 3.43, 3.04),
+            0.1,
+        ));
+        let mat = Arc::new(ThinFilm::new(titanium, thickness, 2.4));
+        let center = Point3::new(4.0 - 4.0 * (i as f64), 1.0, 0.0);
+        world.push(Box::new(Sphere::new(center, 1.0, mat)));
+    }
+
+    world
+}
+
+pub fn layered() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // Solid car paint: a glossy clearcoat over a diffuse base
+    let solid_base = Arc::new(Lambertian::new(Color::new(0.7, 0.05, 0.05)));
+    let mat1 = Arc::new(Layered::new(solid_base, 1.5, 0.05));
+
+    // Metallic car paint: a tinted clearcoat over metallic flakes
+    let flakes = Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.4));
+    let mat2 =
+        Arc::new(Layered::new(flakes, 1.5, 0.05).with_absorption(Color::new(2.0, 1.0, 0.1), 0.3));
+
+    // Varnished wood-like finish with a satin coat
+    let wood_base = Arc::new(OrenNayar::new(Color::new(0.45, 0.25, 0.1), 20.0));
+    let mat3 = Arc::new(Layered::new(wood_base, 1.5, 0.3));
+
+    let sphere1 = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, mat1);
+    let sphere2 = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, mat2);
+    let sphere3 = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, mat3);
+
+    world.push(Box::new(sphere1));
+    world.push(Box::new(sphere2));
+    world.push(Box::new(sphere3));
+
+    world
+}
+
+pub fn weathered() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let steel: Arc<dyn Scatter> = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.75), 0.1));
+    let rust: Arc<dyn Scatter> = Arc::new(OrenNayar::new(Color::new(0.45, 0.18, 0.06), 30.0));
+
+    // Uniformly blended, and with rust patches following a noise pattern
+    let mat1 = Arc::new(MixMaterial::new(steel.clone(), rust.clone(), 0.3));
+    let mat2 = Arc::new(MixMaterial::textured(
+        steel,
+        rust,
+        Arc::new(Noise::new(2.0)),
+    ));
+
+    let sphere1 = Sphere::new(Point3::new(-2.0, 1.0, 0.0), 1.0, mat1);
+    let sphere2 = Sphere::new(Point3::new(2.0, 1.0, 0.0), 1.0, mat2);
+
+    world.push(Box::new(sphere1));
+    world.push(Box::new(sphere2));
+
+    world
+}
+
+/// Uses the tangent-space normal map in the PPM image at `normal_map_path`, or generated tiles
+pub fn normal_map(normal_map_path: Option<String>) -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let normal_map = match normal_map_path {
+        Some(path) => ImageTexture::load(&path)
+            .unwrap_or_else(|e| panic!("Failed to load normal map {}: {}", path, e)),
+        None => tile_normal_map(),
+    };
+
+    let tiles_base = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.4), 0.2));
+    let mat1 = Arc::new(NormalMap::new(tiles_base, Arc::new(normal_map), 1.0));
+
+    let bumpy_base = Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.7)));
+    let mat2 = Arc::new(BumpMap::new(bumpy_base, Arc::new(Noise::new(4.0)), 0.05));
+
+    let sphere1 = Sphere::new(Point3::new(-2.0, 1.0, 0.0), 1.0, mat1);
+    let sphere2 = Sphere::new(Point3::new(2.0, 1.0, 0.0), 1.0, mat2);
+
+    world.push(Box::new(sphere1));
+    world.push(Box::new(sphere2));
+
+    world
+}
+
+/// A tangent-space normal map of 16 by 8 tiles with beveled edges
+fn tile_normal_map() -> ImageTexture {
+    const WIDTH: usize = 512;
+    const HEIGHT: usize = 256;
+    const TILE_SIZE: usize = 32;
+    const BEVEL: f64 = 0.15;
+
+    let mut pixels = Vec::with_capacity(WIDTH * HEIGHT);
+    for j in 0..HEIGHT {
+        for i in 0..WIDTH {
+            // Position within the tile in [0, 1], with rows running upwards
+            let x = ((i % TILE_SIZE) as f64 + 0.5) / (TILE_SIZE as f64);
+            let y = 1.0 - ((j % TILE_SIZE) as f64 + 0.5) / (TILE_SIZE as f64);
+
+            let slope = |t: f64| {
+                if t < BEVEL {
+                    -1.0
+                } else if t > 1.0 - BEVEL {
+                    1.0
+                } else {
+                    0.0
+                }
+            };
+            let n = Vec3::new(slope(x), slope(y), 1.0).normalized();
+
+            pixels.push(Color::new(
+                0.5 * (n.x() + 1.0),
+                0.5 * (n.y() + 1.0),
+                0.5 * (n.z() + 1.0),
+            ));
+        }
+    }
+
+    ImageTexture::new(WIDTH, HEIGHT, pixels)
+}
+
+pub fn alpha_mask() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // A lattice with crisp holes, through which the inside of the sphere can be seen
+    let lattice_opacity = Arc::new(Checker::new(
+        4.0,
+        Arc::new(SolidColor::new(Color::new(1.0, 1.0, 1.0))),
+        Arc::new(SolidColor::new(Color::new(0.0, 0.0, 0.0))),
+    ));
+    let lattice_mat = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.7), 0.3));
+    let lattice_sphere = Sphere::new(Point3::new(-2.0, 1.0, 0.0), 1.0, lattice_mat);
+
+    world.push(Box::new(AlphaMask::new(
+        Box::new(lattice_sphere),
+        lattice_opacity,
+        0.5,
+    )));
+
+    // A cloudy, partially transparent shell around a solid core
+    let shell_mat = Arc::new(Lambertian::new(Color::new(0.2, 0.6, 0.2)));
+    let shell_sphere = Sphere::new(Point3::new(2.0, 1.0, 0.0), 1.0, shell_mat);
+    let core_mat = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.1)));
+    let core_sphere = Sphere::new(Point3::new(2.0, 1.0, 0.0), 0.5, core_mat);
+
+    world.push(Box::new(AlphaMask::stochastic(
+        Box::new(shell_sphere),
+        Arc::new(Noise::new(3.0)),
+    )));
+    world.push(Box::new(core_sphere));
+
+    world
+}
+
+pub fn subsurface() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // Skin lets red light travel much further than blue light
+    let skin_boundary = Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(RoughDielectric::new(1.4, 0.3)),
+    );
+    world.push(Box::new(Subsurface::new(
+        Box::new(skin_boundary),
+        Color::new(0.4, 0.15, 0.08),
+        Color::new(0.95, 0.8, 0.7),
+    )));
+
+    // Wax and marble under a smooth and polished surface
+    let wax_boundary = Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.45)),
+    );
+    world.push(Box::new(Subsurface::new(
+        Box::new(wax_boundary),
+        Color::new(0.3, 0.25, 0.1),
+        Color::new(0.98, 0.9, 0.6),
+    )));
+
+    let marble_boundary = Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    );
+    world.push(Box::new(Subsurface::new(
+        Box::new(marble_boundary),
+        Color::new(0.1, 0.1, 0.1),
+        Color::new(0.99, 0.99, 0.98),
+    )));
+
+    world
+}
+
+/// Uses the raw voxel file at `density_grid_path`, or a procedurally generated cloud
+pub fn cloud(density_grid_path: Option<String>) -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let grid = match density_grid_path {
+        Some(path) => DensityGrid::load_raw(&path)
+            .unwrap_or_else(|e| panic!("Failed to load density grid {}: {}", path, e)),
+        None => DensityGrid::cloud(64, 4.0),
+    };
+
+    // Clouds scatter strongly forward and absorb almost nothing
+    let phase = Arc::new(HenyeyGreenstein::new(Color::new(0.99, 0.99, 0.99), 0.6));
+    let cloud = HeterogeneousMedium::new(
+        grid,
+        Point3::new(-3.0, 0.0, -3.0),
+        Point3::new(3.0, 4.0, 3.0),
+        8.0,
+        phase,
+    );
+
+    world.push(Box::new(cloud));
+
+    world
+}
+
+pub fn lights() -> World {
+    let mut world = World::new();
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let diffuse_mat = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3)));
+    let gold_mat = Arc::new(RoughConductor::gold(0.3));
+    let plastic_mat = Arc::new(Principled::new(Color::new(0.2, 0.4, 0.8)).with_roughness(0.3));
+    let diffuse_sphere = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, diffuse_mat);
+    let gold_sphere = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, gold_mat);
+    let plastic_sphere = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, plastic_mat);
+
+    world.push(Box::new(diffuse_sphere));
+    world.push(Box::new(gold_sphere));
+    world.push(Box::new(plastic_sphere));
+
+    // A warm low sun, a bulb in front of the spheres and a spot light on the right one
+    let sun = DirectionalLight::new(Vec3::new(-1.0, -0.6, -0.4), Color::new(1.5, 1.3, 1.0));
+    let bulb = PointLight::new(Point3::new(0.0, 3.0, 3.0), Color::new(6.0, 6.0, 6.0));
+    let spot = SpotLight::new(
+        Point3::new(6.0, 6.0, 2.0),
+        Point3::new(4.0, 1.0, 0.0),
+        Color::new(40.0, 10.0, 40.0),
+        20.0,
+        10.0,
+    );
+
+    world.push_light(Box::new(sun));
+    world.push_light(Box::new(bulb));
+    world.push_light(Box::new(spot));
+
+    world
+}
+
+/// Lit by the Radiance HDR or OpenEXR environment map at `environment_map_path`, or a generated
+/// sky with a small and very bright sun
+pub fn environment_map(environment_map_path: Option<String>) -> World {
+    let mut world = World::new();
+
+    let environment = match environment_map_path {
+        Some(path) => EnvironmentMap::load(&path)
+            .unwrap_or_else(|e| panic!("Failed to load environment map {}: {}", path, e)),
+        None => sunny_environment(),
+    };
+    world.set_environment(Box::new(
+        environment.with_rotation(30.0).with_intensity(1.0),
+    ));
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let diffuse_mat = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    let conductor_mat = Arc::new(RoughConductor::copper(0.25));
+    let glass_mat = Arc::new(RoughDielectric::new(1.5, 0.1));
+    let diffuse_sphere = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, diffuse_mat);
+    let conductor_sphere = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, conductor_mat);
+    let glass_sphere = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, glass_mat);
+
+    world.push(Box::new(diffuse_sphere));
+    world.push(Box::new(conductor_sphere));
+    world.push(Box::new(glass_sphere));
+
+    world
+}
+
+/// A latitude-longitude map of a blue sky fading to a hazy horizon above dark ground, with a sun
+/// of about a degree in radius
+fn sunny_environment() -> EnvironmentMap {
+    const WIDTH: usize = 512;
+    const HEIGHT: usize = 256;
+    const SUN_RADIUS: f64 = 0.02;
+    const SUN_RADIANCE: f64 = 5000.0;
+
+    let sun_direction = Vec3::new(1.0, 0.6, 0.5).normalized();
+
+    let mut pixels = Vec::with_capacity(WIDTH * HEIGHT);
+    for j in 0..HEIGHT {
+        for i in 0..WIDTH {
+            let theta = PI * (j as f64 + 0.5) / (HEIGHT as f64);
+            let phi = 2.0 * PI * (i as f64 + 0.5) / (WIDTH as f64);
+            let direction = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+
+            let pixel = if direction.dot(sun_direction) > SUN_RADIUS.cos() {
+                Color::new(SUN_RADIANCE, 0.9 * SUN_RADIANCE, 0.8 * SUN_RADIANCE)
+            } else if direction.y() > 0.0 {
+                let t = direction.y().sqrt();
+                (1.0 - t) * Color::new(0.45, 0.45, 0.5) + t * Color::new(0.1, 0.2, 0.45)
+            } else {
+                Color::new(0.08, 0.06, 0.05)
+            };
+            pixels.push(pixel);
+        }
+    }
+
+    EnvironmentMap::new(WIDTH, HEIGHT, pixels)
+}
+
+/// An outdoor scene under a clear sky, with the sun at `sun_elevation` degrees above the horizon
+pub fn sky(sun_elevation: Option<String>) -> World {
+    let mut world = World::new();
+
+    let sun_elevation = match sun_elevation {
+        Some(elevation) => elevation
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid sun elevation {}: {}", elevation, e)),
+        None => 30.0,
+    };
+    world.set_environment(Box::new(
+        Sky::new(sun_elevation, 60.0, 3.0).with_intensity(0.6),
+    ));
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.4, 0.4, 0.35)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let diffuse_mat = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    let metal_mat = Arc::new(RoughConductor::aluminium(0.1));
+    let plastic_mat = Arc::new(Principled::new(Color::new(0.8, 0.2, 0.1)).with_roughness(0.4));
+    let diffuse_sphere = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, diffuse_mat);
+    let metal_sphere = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, metal_mat);
+    let plastic_sphere = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, plastic_mat);
+
+    world.push(Box::new(diffuse_sphere));
+    world.push(Box::new(metal_sphere));
+    world.push(Box::new(plastic_sphere));
+
+    world
+}
+
+/// Spheres lit only by a thousand small glowing orbs of random colors floating above them
+pub fn many_lights() -> World {
+    let mut rng = rand::thread_rng();
+    let mut world = World::new();
+
+    let black = Color::new(0.0, 0.0, 0.0);
+    world.set_environment(Box::new(Gradient::new(black, black)));
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let diffuse_mat = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    let metal_mat = Arc::new(RoughConductor::silver(0.2));
+    let plastic_mat = Arc::new(Principled::new(Color::new(0.1, 0.5, 0.2)).with_roughness(0.3));
+    let diffuse_sphere = Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, diffuse_mat);
+    let metal_sphere = Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, metal_mat);
+    let plastic_sphere = Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, plastic_mat);
+
+    world.push(Box::new(diffuse_sphere));
+    world.push(Box::new(metal_sphere));
+    world.push(Box::new(plastic_sphere));
+
+    for i in 0..1000 {
+        let center = Point3::new(
+            rng.gen_range(-20.0..10.0),
+            rng.gen_range(0.2..4.0),
+            rng.gen_range(-10.0..10.0),
+        );
+        if (center - Point3::new(0.0, 1.0, 0.0)).length() < 6.0 && i % 10 != 0 {
+            // Keep most of them clear of the spheres in the center
+            continue;
+        }
+
+        let emit = 20.0 * Color::random(0.05..1.0);
+        let light_mat = Arc::new(DiffuseLight::new(emit));
+        world.push(Box::new(Sphere::new(center, 0.05, light_mat)));
+    }
+
+    world
+}
+
+pub fn mesh_light() -> World {
+    let mut world = World::new();
+
+    let black = Color::new(0.0, 0.0, 0.0);
+    world.set_environment(Box::new(Gradient::new(black, black)));
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    // A panel facing down, with warm and cool stripes mapped across it
+    let stripes = (0..8)
+        .map(|i| {
+            if i % 2 == 0 {
+                Color::new(8.0, 6.0, 3.0)
+            } else {
+                Color::new(2.0, 3.0, 8.0)
+            }
+        })
+        .collect();
+    let panel_tex = Arc::new(ImageTexture::new(8, 1, stripes));
+    let panel_mat = Arc::new(DiffuseLight::textured(panel_tex));
+    world.push(Box::new(quad(
+        Point3::new(-3.0, 5.0, -2.0),
+        Vec3::new(6.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        panel_mat,
+    )));
+
+    // A tetrahedron with its faces pointing outwards
+    let positions = vec![
+        Point3::new(-1.0, 0.0, 1.0),
+        Point3::new(1.0, 0.0, 1.0),
+        Point3::new(0.0, 0.0, -1.0),
+        Point3::new(0.0, 1.8, 0.3),
+    ];
+    let triangles = vec![[0, 2, 1], [0, 1, 3], [1, 2, 3], [2, 0, 3]];
+    let tetrahedron_mat = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.2)));
+    world.push(Box::new(Mesh::new(
+        positions,
+        Vec::new(),
+        triangles,
+        tetrahedron_mat,
+    )));
+
+    let metal_mat = Arc::new(RoughConductor::silver(0.2));
+    let diffuse_mat = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    let metal_sphere = Sphere::new(Point3::new(-2.5, 0.8, 0.0), 0.8, metal_mat);
+    let diffuse_sphere = Sphere::new(Point3::new(2.5, 0.8, 0.0), 0.8, diffuse_mat);
+
+    world.push(Box::new(metal_sphere));
+    world.push(Box::new(diffuse_sphere));
+
+    world
+}
+
+/// A parallelogram of two triangles spanned by `u` and `v` from `corner`, facing towards
+/// `u × v` and textured from `(0, 0)` at the corner to `(1, 1)` at the opposite one
+fn quad(corner: Point3, u: Vec3, v: Vec3, material: Arc<dyn Scatter>) -> Mesh {
+    let positions = vec![corner, corner + u, corner + u + v, corner + v];
+    let uvs = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+    Mesh::new(positions, uvs, vec![[0, 1, 2], [0, 2, 3]], material)
+}
+
+/// Wall washers casting the photometric profile of the IES file at `ies_path`, or of a sample
+/// asymmetric spot, onto a wall behind the spheres
+pub fn ies(ies_path: Option<String>) -> World {
+    let mut world = World::new();
+
+    let dark = Color::new(0.02, 0.02, 0.03);
+    world.set_environment(Box::new(Gradient::new(dark, dark)));
+
+    let profile = match ies_path {
+        Some(path) => IesProfile::load(&path)
+            .unwrap_or_else(|e| panic!("Failed to load IES profile {}: {}", path, e)),
+        None => IesProfile::parse(SAMPLE_IES)
+            .unwrap_or_else(|e| panic!("Failed to parse the sample IES profile: {}", e)),
+    };
+    let profile = Arc::new(profile);
+
+    let ground_mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let ground_sphere = Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat);
+
+    world.push(Box::new(ground_sphere));
+
+    let wall_mat = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    world.push(Box::new(quad(
+        Point3::new(-4.0, 0.0, -12.0),
+        Vec3::new(0.0, 8.0, 0.0),
+        Vec3::new(0.0, 0.0, 24.0),
+        wall_mat,
+    )));
+
+    let diffuse_mat = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3)));
+    let plastic_mat = Arc::new(Principled::new(Color::new(0.2, 0.4, 0.8)).with_roughness(0.3));
+    let diffuse_sphere = Sphere::new(Point3::new(0.0, 0.8, -1.5), 0.8, diffuse_mat);
+    let plastic_sphere = Sphere::new(Point3::new(0.0, 0.8, 1.5), 0.8, plastic_mat);
+
+    world.push(Box::new(diffuse_sphere));
+    world.push(Box::new(plastic_sphere));
+
+    // Hanging close to the wall and aimed down at it, turned differently to show the asymmetry
+    for (i, z) in [-3.5, 0.0, 3.5].into_iter().enumerate() {
+        let light = GoniometricLight::new(
+            Point3::new(-3.0, 3.5, z),
+            Point3::new(-4.0, 1.0, z),
+            Color::new(30.0, 27.0, 22.0),
+            profile.clone(),
+        )
+        .with_rotation(90.0 * i as f64);
+        world.push_light(Box::new(light));
+    }
+
+    world
+}
+
+/// A spot in IESNA LM-63 format with a bright central beam that spreads more to one side
+const SAMPLE_IES: &str = "IESNA:LM-63-2002
+[TEST] SAMPLE
+[MANUFAC] RAYTRACER
+[LUMINAIRE] ASYMMETRIC SPOT
+TILT=NONE
+1 1000 1.0 10 3 1 2 0.1 0.1 0.0
+1.0 1.0 20
+0 10 20 30 40 50 60 70 80 90
+0 90 180
+1000 950 800 600 400 250 120 40 10 0
+1000 900 600 250 80 20 5 0 0 0
+1000 820 450 150 40 10 0 0 0 0
+";
@@ -1,9 +1,13 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::Rng;
 
 use crate::hit::{Hit, HitRecord};
 use crate::material::Scatter;
+use crate::onb::Onb;
 use crate::point3::Point3;
 use crate::ray::Ray;
+use crate::vec3::Vec3;
 
 pub struct Sphere {
     center: Point3,
@@ -19,6 +23,49 @@ impl Sphere {
             material,
         }
     }
+
+    /// Maps a point on the unit sphere to its longitude and latitude in `[0, 1]`
+    fn uv(p: Vec3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// The partial derivatives of the point on the sphere in the direction `p` from its center
+    /// with respect to the longitude and latitude from `uv`
+    fn derivatives(&self, p: Vec3) -> (Vec3, Vec3) {
+        let sin_theta = (p.x().powi(2) + p.z().powi(2)).sqrt();
+        if sin_theta < 1.0e-9 {
+            // The poles, where any frame perpendicular to the normal will do
+            return (
+                (2.0 * PI * self.radius) * Vec3::new(1.0, 0.0, 0.0),
+                (PI * self.radius) * Vec3::new(0.0, 0.0, 1.0),
+            );
+        }
+
+        let dpdu = (2.0 * PI * self.radius) * Vec3::new(p.z(), 0.0, -p.x());
+        let dpdv = (PI * self.radius)
+            * Vec3::new(
+                -p.y() * p.x() / sin_theta,
+                sin_theta,
+                -p.y() * p.z() / sin_theta,
+            );
+
+        (dpdu, dpdv)
+    }
+
+    /// One minus the cosine of the half-angle of the cone of directions from a point outside the
+    /// sphere towards it
+    fn cone(&self, origin: Point3) -> Option<f64> {
+        let sin2_theta_max = self.radius.powi(2) / (self.center - origin).length().powi(2);
+        if sin2_theta_max >= 1.0 {
+            return None;
+        }
+
+        // Written to stay accurate for small and distant spheres
+        Some(sin2_theta_max / (1.0 + (1.0 - sin2_theta_max).sqrt()))
+    }
 }
 
 impl Hit for Sphere {
@@ -49,10 +96,86 @@ impl Hit for Sphere {
             point_hit,
             self.material.clone(),
             t_hit,
-            &ray,
+            Self::uv(outward_normal),
+            ray,
             outward_normal,
+            self.derivatives(outward_normal),
         );
 
         Some(rec)
     }
+
+    fn power(&self) -> f64 {
+        // Average the emitted radiance over a grid of points on the surface, each seen from
+        // straight above
+        const STEPS: usize = 8;
+        let mut luminance = 0.0;
+        let mut total_weight = 0.0;
+        for i in 0..STEPS {
+            for j in 0..2 * STEPS {
+                let theta = PI * (i as f64 + 0.5) / (STEPS as f64);
+                let phi = PI * (j as f64 + 0.5) / (STEPS as f64);
+                let normal = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+
+                let origin = self.center + (2.0 * self.radius.abs()) * normal;
+                let ray = Ray::new(origin, (-1.0) * normal);
+                if let Some(rec) = self.hit(&ray, 0.0, f64::INFINITY) {
+                    luminance += theta.sin() * rec.material.emitted(&rec).luminance();
+                    total_weight += theta.sin();
+                }
+            }
+        }
+
+        let area = 4.0 * PI * self.radius.powi(2);
+        PI * area * luminance / total_weight.max(1.0e-12)
+    }
+
+    fn bounding_box(&self) -> Option<(Point3, Point3)> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some((self.center - extent, self.center + extent))
+    }
+
+    fn sample_point(&self, origin: Point3) -> Option<(HitRecord, f64)> {
+        let mut rng = rand::thread_rng();
+
+        let Some(one_minus_cos) = self.cone(origin) else {
+            // From inside, sample the whole surface uniformly by area
+            let point = self.center + self.radius * Vec3::random_in_unit_sphere().normalized();
+            let ray = Ray::new(origin, point - origin);
+            let rec = self.hit(&ray, 1.0e-6, f64::INFINITY)?;
+            let pdf = self.point_pdf(origin, &rec);
+            return Some((rec, pdf));
+        };
+
+        // Uniformly within the cone of directions towards the sphere
+        let one_minus_cos_theta = rng.gen::<f64>() * one_minus_cos;
+        let cos_theta = 1.0 - one_minus_cos_theta;
+        let sin_theta = (one_minus_cos_theta * (2.0 - one_minus_cos_theta)).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let direction = Onb::from_w(self.center - origin).to_world(Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+
+        let rec = self.hit(&Ray::new(origin, direction), 1.0e-6, f64::INFINITY)?;
+        Some((rec, 1.0 / (2.0 * PI * one_minus_cos)))
+    }
+
+    fn point_pdf(&self, origin: Point3, rec: &HitRecord) -> f64 {
+        match self.cone(origin) {
+            Some(one_minus_cos) => 1.0 / (2.0 * PI * one_minus_cos),
+            None => {
+                let to_point = rec.point - origin;
+                let cos_theta = rec.normal.dot(to_point.normalized()).abs();
+                let area = 4.0 * PI * self.radius.powi(2);
+
+                to_point.length().powi(2) / (cos_theta * area).max(1.0e-12)
+            }
+        }
+    }
 }
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::color::Color;
+use crate::hit::{Hit, HitRecord};
+use crate::material::Scatter;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// The number of scattering events after which a walk through the volume is given up
+const MAX_EVENTS: usize = 256;
+
+/// Turns a closed object into a translucent volume like skin, wax or marble.
+///
+/// Light enters through the material of the boundary, typically a dielectric, and then performs
+/// a random walk of scattering events inside until it leaves through the boundary again.
+pub struct Subsurface {
+    medium: Arc<Medium>,
+}
+
+impl Subsurface {
+    pub fn new(boundary: Box<dyn Hit>, mean_free_path: Color, albedo: Color) -> Subsurface {
+        let medium = Medium {
+            boundary: Arc::from(boundary),
+            extinction: Color::new(
+                1.0 / mean_free_path[0],
+                1.0 / mean_free_path[1],
+                1.0 / mean_free_path[2],
+            ),
+            albedo,
+        };
+
+        Subsurface {
+            medium: Arc::new(medium),
+        }
+    }
+}
+
+impl Hit for Subsurface {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.medium.boundary.hit(r, t_min, t_max)?;
+        if !rec.front_face {
+            // Light that entered through the boundary's material walks through the volume
+            // before it reaches the boundary from inside
+            rec.material = self.medium.clone();
+        }
+
+        Some(rec)
+    }
+
+    /// The light passing straight through the volume without being scattered, ignoring the
+    /// refraction at the boundary
+    fn transmittance(&self, r: &Ray, t_min: f64, t_max: f64) -> Color {
+        let mut length = 0.0;
+        let mut entered = t_min;
+        let mut inside = false;
+        let mut t = t_min;
+
+        while let Some(rec) = self.medium.boundary.hit(r, t, t_max) {
+            inside = rec.front_face;
+            if inside {
+                entered = rec.t;
+            } else {
+                // Leaving the volume, which the ray may have started in
+                length += rec.t - entered;
+            }
+
+            t = rec.t + 0.001;
+        }
+        if inside {
+            length += t_max - entered;
+        }
+
+        self.medium.transmittance(length * r.direction().length())
+    }
+}
+
+/// The inside of the volume, which traces the whole random walk of the light from where it
+/// entered to where it leaves through the boundary
+struct Medium {
+    boundary: Arc<dyn Hit>,
+    /// The extinction coefficient per color channel, the inverse of the mean free path
+    extinction: Color,
+    /// The probability of light being scattered instead of absorbed at each event
+    albedo: Color,
+}
+
+impl Medium {
+    fn transmittance(&self, distance: f64) -> Color {
+        (-distance * self.extinction).exp()
+    }
+}
+
+impl Scatter for Medium {
+    fn scatter(&self, r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Ray)> {
+        let mut rng = rand::thread_rng();
+        let mut ray = Ray::with_wavelength(
+            r_in.origin(),
+            r_in.direction().normalized(),
+            r_in.wavelength(),
+        );
+
+        // Sample the free-flight distances for a random color channel, and weight the walk by its
+        // average density over all channels to get rid of the noise of picking one. The densities
+        // are kept relative to the picked channel, which keeps them from underflowing.
+        let channel = rng.gen_range(0..3);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut density = Color::new(1.0, 1.0, 1.0);
+        let weight = |throughput: &mut Color, density: &mut Color, value: Color| {
+            let scale = 1.0 / (density[channel] * value[channel]);
+            *throughput = scale * (*throughput * value);
+            *density = scale * (*density * value);
+        };
+
+        for _ in 0..MAX_EVENTS {
+            // The boundary is intersected again rather than taken from the record, whose material
+            // has been replaced by this one
+            let exit = self.boundary.hit(&ray, 0.001, f64::INFINITY)?;
+            let distance = -(1.0 - rng.gen::<f64>()).ln() / self.extinction[channel];
+
+            if distance < exit.t {
+                let collision = self.extinction * self.transmittance(distance);
+                weight(&mut throughput, &mut density, collision);
+                throughput = throughput * self.albedo;
+                if throughput.luminance() <= 0.0 {
+                    return None;
+                }
+
+                // Scatter equally in all directions
+                let direction = Vec3::random_in_unit_sphere().normalized();
+                ray = Ray::with_wavelength(ray.at(distance), direction, ray.wavelength());
+                continue;
+            }
+
+            weight(&mut throughput, &mut density, self.transmittance(exit.t));
+
+            // Leave through the boundary's material, unless it reflects the light back inside
+            let (attenuation, scattered) = exit.material.scatter(&ray, &exit)?;
+            throughput = attenuation * throughput;
+            if scattered.direction().dot(exit.normal) < 0.0 {
+                let average = (density[0] + density[1] + density[2]) / 3.0;
+                return Some(((1.0 / average) * throughput, scattered));
+            }
+
+            ray = Ray::with_wavelength(
+                scattered.origin(),
+                scattered.direction().normalized(),
+                scattered.wavelength(),
+            );
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Dielectric;
+    use crate::point3::Point3;
+    use crate::sphere::Sphere;
+
+    /// The average weight of the light leaving a unit ball with an index-matched boundary after
+    /// entering it head-on
+    fn albedo(mean_free_path: Color, albedo: Color) -> Color {
+        let boundary = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Dielectric::new(1.0)),
+        );
+        let volume = Subsurface::new(Box::new(boundary), mean_free_path, albedo);
+        let r = Ray::new(Point3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let samples = 5000;
+        let mut total = Color::new(0.0, 0.0, 0.0);
+        for _ in 0..samples {
+            let entry = volume.hit(&r, 0.001, f64::INFINITY).unwrap();
+            let (_, inside) = entry.material.scatter(&r, &entry).unwrap();
+            let rec = volume.hit(&inside, 0.001, f64::INFINITY).unwrap();
+            assert!(!rec.front_face);
+
+            if let Some((attenuation, scattered)) = rec.material.scatter(&inside, &rec) {
+                // The light leaves from the boundary, away from the ball
+                let origin = scattered.origin() - Point3::new(0.0, 0.0, 0.0);
+                assert!((origin.length() - 1.0).abs() < 1.0e-6);
+                assert!(scattered.direction().dot(origin) > 0.0);
+                total += attenuation;
+            }
+        }
+
+        (1.0 / samples as f64) * total
+    }
+
+    #[test]
+    fn shadows_by_the_unscattered_light() {
+        let boundary = Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            Arc::new(Dielectric::new(1.5)),
+        );
+        let volume = Subsurface::new(
+            Box::new(boundary),
+            Color::new(0.5, 1.0, 2.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+
+        // Through the whole ball, out of it from its center, and up to its center
+        let direction = Vec3::new(0.0, 0.0, 2.0);
+        let through = Ray::new(Point3::new(0.0, 0.0, -3.0), direction);
+        let from_center = Ray::new(Point3::new(0.0, 0.0, 0.0), direction);
+        for (r, t_max, length) in [
+            (&through, 3.0, 2.0),
+            (&from_center, 3.0, 1.0),
+            (&through, 1.5, 1.0),
+        ] {
+            let transmittance = volume.transmittance(r, 0.001, t_max);
+            for (channel, mean_free_path) in [0.5_f64, 1.0, 2.0].into_iter().enumerate() {
+                let expected = (-length / mean_free_path).exp();
+                assert!((transmittance[channel] - expected).abs() < 1.0e-2);
+            }
+        }
+
+        let miss = Ray::new(Point3::new(2.0, 0.0, -3.0), direction);
+        assert_eq!(volume.transmittance(&miss, 0.001, 3.0)[0], 1.0);
+    }
+
+    #[test]
+    fn black_volume_absorbs_everything() {
+        let albedo = albedo(Color::new(0.01, 0.01, 0.01), Color::new(0.0, 0.0, 0.0));
+        assert_eq!((albedo[0], albedo[1], albedo[2]), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn white_volume_conserves_energy() {
+        let gray = albedo(Color::new(0.25, 0.25, 0.25), Color::new(1.0, 1.0, 1.0));
+        assert!(gray[0] > 0.98 && gray[0] <= 1.0 + 1.0e-9, "{}", gray[0]);
+
+        // Sampling the distances for a random channel is unbiased for the others
+        let colored = albedo(Color::new(0.2, 0.4, 0.8), Color::new(1.0, 1.0, 1.0));
+        for channel in 0..3 {
+            assert!(
+                (colored[channel] - 1.0).abs() < 0.05,
+                "{}",
+                colored[channel]
+            );
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use crate::{color::Color, point3::Point3};
+
+use super::Texture;
+
+/// Alternates between two textures in a 3D checkerboard pattern
+pub struct Checker {
+    /// The number of squares per unit of length
+    scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Checker {
+        Checker { scale, even, odd }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let cell = (self.scale * p.x()).floor()
+            + (self.scale * p.y()).floor()
+            + (self.scale * p.z()).floor();
+
+        if (cell as i64).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::{color::Color, point3::Point3};
+
+use super::Texture;
+
+/// A texture backed by an image, which is looked up by the surface coordinates of the hit point
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Pixels in rows from top to bottom
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> ImageTexture {
+        assert!(width > 0 && height > 0, "Image without pixels");
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "Image size doesn't match its pixels"
+        );
+
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The width and height in pixels
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Loads a plain (P3) or binary (P6) PPM image, with values as stored in the file scaled to
+    /// `[0, 1]`
+    pub fn load(path: impl AsRef<Path>) -> io::Result<ImageTexture> {
+        ImageTexture::parse(&fs::read(path)?)
+    }
+
+    /// Parses the contents of a P3 or P6 PPM file
+    pub fn parse(data: &[u8]) -> io::Result<ImageTexture> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        // The header consists of four whitespace separated tokens, with comments starting at '#'
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        while tokens.len() < 4 {
+            while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+                if data[pos] == b'#' {
+                    while pos < data.len() && data[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid("Truncated PPM header"));
+            }
+            tokens.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+        }
+
+        let parse = |token: &str| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid("Invalid number in PPM header"))
+        };
+        let width = parse(&tokens[1])?;
+        let height = parse(&tokens[2])?;
+        let max_value = parse(&tokens[3])?;
+        if width == 0 || height == 0 {
+            return Err(invalid("PPM image without pixels"));
+        }
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("PPM maximum value out of range"));
+        }
+        let value_count = width
+            .checked_mul(height)
+            .and_then(|count| count.checked_mul(3))
+            .ok_or_else(|| invalid("PPM image too large"))?;
+        let max_value = max_value as f64;
+
+        let values: Vec<f64> = match tokens[0].as_str() {
+            "P3" => String::from_utf8_lossy(&data[pos..])
+                .split_ascii_whitespace()
+                .map(|value| value.parse::<f64>().map(|value| value / max_value))
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid("Invalid pixel value"))?,
+            "P6" if max_value < 256.0 => data[(pos + 1).min(data.len())..]
+                .iter()
+                .map(|&value| (value as f64) / max_value)
+                .collect(),
+            "P6" => data[(pos + 1).min(data.len())..]
+                .chunks_exact(2)
+                .map(|value| (u16::from_be_bytes([value[0], value[1]]) as f64) / max_value)
+                .collect(),
+            _ => return Err(invalid("Only P3 and P6 PPM images are supported")),
+        };
+
+        if values.len() < value_count {
+            return Err(invalid("Truncated PPM pixel data"));
+        }
+
+        let pixels = values
+            .chunks_exact(3)
+            .take(width * height)
+            .map(|rgb| Color::new(rgb[0], rgb[1], rgb[2]))
+            .collect();
+
+        Ok(ImageTexture::new(width, height, pixels))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        // Wrap around the texture, with v pointing upwards
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[j * self.width + i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_pixel(image: &ImageTexture, index: usize, expected: [f64; 3]) {
+        for (channel, expected) in expected.into_iter().enumerate() {
+            assert!((image.pixels[index][channel] - expected).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn plain() {
+        let image = ImageTexture::parse(b"P3\n# A comment\n2 1\n255\n255 0 0  0 51 255\n").unwrap();
+
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_pixel(&image, 0, [1.0, 0.0, 0.0]);
+        assert_pixel(&image, 1, [0.0, 0.2, 1.0]);
+    }
+
+    #[test]
+    fn binary() {
+        let mut data = b"P6 1 2 255\n".to_vec();
+        data.extend([255, 0, 51, 0, 102, 255]);
+        let image = ImageTexture::parse(&data).unwrap();
+
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_pixel(&image, 0, [1.0, 0.0, 0.2]);
+        assert_pixel(&image, 1, [0.0, 0.4, 1.0]);
+    }
+
+    #[test]
+    fn binary_16_bit() {
+        let mut data = b"P6 1 1 65535\n".to_vec();
+        data.extend([0xff, 0xff, 0x00, 0x00, 0x80, 0x00]);
+        let image = ImageTexture::parse(&data).unwrap();
+
+        assert_pixel(&image, 0, [1.0, 0.0, 32768.0 / 65535.0]);
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(ImageTexture::parse(b"P3 2 2").is_err());
+        assert!(ImageTexture::parse(b"P3 2 2 255 1 2 3 4 5 6").is_err());
+
+        let mut data = b"P6 2 1 255\n".to_vec();
+        data.extend([1, 2, 3, 4, 5]);
+        assert!(ImageTexture::parse(&data).is_err());
+    }
+
+    #[test]
+    fn invalid_header() {
+        let error = |data: &[u8]| ImageTexture::parse(data).err().unwrap().kind();
+
+        assert_eq!(error(b"P3 0 1 255\n"), ErrorKind::InvalidData);
+        assert_eq!(error(b"P3 1 0 255\n"), ErrorKind::InvalidData);
+        assert_eq!(error(b"P3 1 1 0\n0 0 0\n"), ErrorKind::InvalidData);
+        assert_eq!(
+            error(b"P6 18446744073709551615 2 255\n"),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(error(b"P5 1 1 255\n0"), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,17 @@
+mod checker;
+mod image;
+mod noise;
+mod perlin;
+mod solid_color;
+
+pub use checker::Checker;
+pub use image::ImageTexture;
+pub use noise::Noise;
+pub use perlin::Perlin;
+pub use solid_color::SolidColor;
+
+use crate::{color::Color, point3::Point3};
+
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
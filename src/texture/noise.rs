@@ -0,0 +1,28 @@
+use crate::{color::Color, point3::Point3};
+
+use super::{Perlin, Texture};
+
+/// Grayscale turbulence, varying between black and white
+pub struct Noise {
+    perlin: Perlin,
+    /// The frequency of the noise per unit of length
+    scale: f64,
+}
+
+impl Noise {
+    pub fn new(scale: f64) -> Noise {
+        Noise {
+            perlin: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let scaled = Point3::new(self.scale * p.x(), self.scale * p.y(), self.scale * p.z());
+        let value = self.perlin.turbulence(scaled, 7).min(1.0);
+
+        Color::new(value, value, value)
+    }
+}
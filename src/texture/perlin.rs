@@ -0,0 +1,85 @@
+use rand::seq::SliceRandom;
+
+use crate::{point3::Point3, vec3::Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise on a 3D lattice (Perlin 1985)
+pub struct Perlin {
+    random_vectors: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new() -> Perlin {
+        let random_vectors = (0..POINT_COUNT)
+            .map(|_| Vec3::random(-1.0..1.0).normalized())
+            .collect();
+
+        Perlin {
+            random_vectors,
+            perm_x: Self::generate_perm(),
+            perm_y: Self::generate_perm(),
+            perm_z: Self::generate_perm(),
+        }
+    }
+
+    fn generate_perm() -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+        perm.shuffle(&mut rand::thread_rng());
+        perm
+    }
+
+    /// Smoothly varying noise in `[-1, 1]`
+    pub fn noise(&self, p: Point3) -> f64 {
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
+
+        let i = p.x().floor() as i64;
+        let j = p.y().floor() as i64;
+        let k = p.z().floor() as i64;
+
+        // Hermite smoothing of the interpolation weights
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize];
+                    let weight = Vec3::new(u - di as f64, v - dj as f64, w - dk as f64);
+
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * self.random_vectors[index].dot(weight);
+                }
+            }
+        }
+
+        accum
+    }
+
+    /// Sum of noise at increasing frequencies and decreasing amplitudes
+    pub fn turbulence(&self, p: Point3, depth: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(p);
+            weight *= 0.5;
+            p = Point3::new(2.0 * p.x(), 2.0 * p.y(), 2.0 * p.z());
+        }
+
+        accum.abs()
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{color::Color, point3::Point3};
+
+use super::Texture;
+
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.color
+    }
+}
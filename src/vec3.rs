@@ -97,6 +97,21 @@ impl Vec3 {
         r_out_perp + r_out_parallel
     }
 
+    /// A random direction in the upper hemisphere around the z-axis, distributed proportionally
+    /// to the cosine of its angle with the z-axis
+    pub fn random_cosine_direction() -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let r1: f64 = rng.gen();
+        let r2: f64 = rng.gen();
+
+        let phi = 2.0 * std::f64::consts::PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1.0 - r2).sqrt();
+
+        Vec3::new(x, y, z)
+    }
+
     pub fn random_in_unit_disk() -> Vec3 {
         let mut rng = rand::thread_rng();
 
@@ -176,7 +191,7 @@ impl Mul<f64> for Vec3 {
 }
 
 impl MulAssign<f64> for Vec3 {
-    fn mul_assign(&mut self, other: f64) -> () {
+    fn mul_assign(&mut self, other: f64) {
         *self = Vec3 {
             e: [self[0] * other, self[1] * other, self[2] * other],
         };
@@ -204,7 +219,7 @@ impl Div<f64> for Vec3 {
 }
 
 impl DivAssign<f64> for Vec3 {
-    fn div_assign(&mut self, other: f64) -> () {
+    fn div_assign(&mut self, other: f64) {
         *self = Vec3 {
             e: [self[0] / other, self[1] / other, self[2] / other],
         };